@@ -1,82 +1,183 @@
-use std::collections::{HashMap, BinaryHeap};
-use crate::{Map, Move};
+use crate::{Map, Move, Position};
 use crate::node::Node;
 use crate::solver::Solver;
-use crate::display::{Info, Debug, Solution, State};
+use crate::error::Error;
+use crate::observer::Event;
+use crate::display::{Debug, Solution, State, Interrupted};
+use crate::tree::Tree;
+use crate::open_list::OpenList;
+use crate::optimizer;
+use crate::zobrist::{Key, ZobristMap};
 
-pub fn solve(start: Map, solver: Solver) -> Result<(), String>
+pub fn solve(start: Map, solver: Solver) -> Result<(), Error>
+{
+	let mut solution = find_path(start, &solver)?;
+	optimizer::apply(&mut solution, &solver);
+	solution.display(solver.size, solver.flag.verbosity, solver.time);
+	Ok(())
+}
+
+pub fn find_path(start: Map, solver: &Solver) -> Result<Solution, Error>
+{
+	search(start, solver, None)
+}
+
+// Same as find_path but also records the graph explored by the search, up to 'cap' states
+pub fn find_path_tree(start: Map, solver: &Solver, cap: usize) -> Result<(Solution, Tree), Error>
+{
+	let mut tree = Tree::new(solver.size, cap);
+	let solution = search(start, solver, Some(&mut tree))?;
+	Ok((solution, tree))
+}
+
+pub fn solve_tree(start: Map, solver: Solver, filepath: &str, cap: usize) -> Result<(), Error>
+{
+	let (mut solution, tree) = find_path_tree(start, &solver, cap)?;
+	tree.create_file(filepath)?;
+	optimizer::apply(&mut solution, &solver);
+	solution.display(solver.size, solver.flag.verbosity, solver.time);
+	Ok(())
+}
+
+fn search(start: Map, solver: &Solver, mut tree: Option<&mut Tree>) -> Result<Solution, Error>
 {
 	let mut start = Node::new(start);
 	start.find_position(solver.size);
 	start = solver.get_cost(start);
 
-	let mut open_set: BinaryHeap<Node> = BinaryHeap::new();
-	let mut closed_set: HashMap<Map, Move> = HashMap::new();
+	let mut open_set = OpenList::new(solver.queue, solver.tie);
+	// Move leading to each state, with the position of the blank that moved
+	let mut closed_set: ZobristMap<(Move, Position)> = ZobristMap::default();
 
-	let mut info = Info::new(start.h);
+	if !solver.flag.debug { solver.notify(Event::Started { h: start.h }) }
 	let mut debug = Debug { parent_count: 1, child_count: 1 };
 
+	// Map of the closed node with the lowest h, reported if the search is cancelled
+	let mut best = (start.h, start.map.clone());
+	if let Some(tree) = tree.as_deref_mut() { tree.generated(&start, None) }
 	open_set.push(start);
 
-	let (mut last_pos, last_move) = loop
+	loop
 	{
 		// Get the node with the lowest f cost
-		let mut current = open_set.pop().unwrap();
+		let mut current = match open_set.pop()
+		{
+			Some(node) => node,
+			None => return Err(Error::Exhausted("no state reachable from the start is the goal".to_owned()))
+		};
+
+		if solver.cancelled()
+		{
+			let bound = current.f;
+			let stats = vec![("Number of pending states (open set)", open_set.len() + 1), ("Number of selected states (closed set)", closed_set.len())];
+			let (best_h, map) = best;
+			let path = path_to(&closed_set, map, solver)?;
+			return Err(Error::Interrupted(Box::new(Interrupted { path, best_h, bound, stats })))
+		}
+		// A state already reached with a lower g may still be pending, keep the path found first
+		if closed_set.contains_key(&Key::new(&current, solver.confirm)) { continue }
 
 		if solver.flag.debug
 		{
 			current = debug.parent(current, solver.size, open_set.len(), closed_set.len());
 		}
-		else if current.h < info.min_h
+		else if current.h < best.0
 		{
-			info.update(current.h, open_set.len(), closed_set.len());
+			solver.notify(Event::Best { h: current.h });
+			solver.notify(Event::Memory(vec![("open states", open_set.len()), ("closed states", closed_set.len()),
+				("total states", open_set.len() + closed_set.len())]));
 		}
 
+		let parent = tree.as_deref_mut().and_then(|tree| tree.expanded(&current));
+
 		// If the solution is found
-		if current.h == 0
+		if solver.is_goal(&current)
 		{
-			let end_pos = current.pos.clone();
-			let end_move = current.movement.clone();
-			closed_set.insert(current.map, current.movement.clone());
-			break (end_pos, end_move)
+			best = (current.h, current.map.clone());
+			closed_set.insert(Key::new(&current, solver.confirm), (current.movement, current.pos));
+			break
 		}
 
 		// Get the list of possible moves
-		let moves: Vec<Node> = current.generate_moves(solver.size);
+		let moves: Vec<Node> = current.generate_moves(solver.size, &solver.rules);
 
-		closed_set.insert(current.map, current.movement.clone());
+		if current.h < best.0 { best = (current.h, current.map.clone()) }
+		closed_set.insert(Key::new(&current, solver.confirm), (current.movement.clone(), current.pos.clone()));
 
 		// Get the costs of child nodes and push them in the open set
 		for mut node in moves
 		{
-			if closed_set.contains_key(&node.map) { continue }
+			if closed_set.contains_key(&Key::new(&node, solver.confirm)) { continue }
 			node = solver.update_cost(node);
 
 			if solver.flag.debug { debug.child(&node) }
+			if let (Some(tree), Some(parent)) = (tree.as_deref_mut(), parent) { tree.generated(&node, Some(parent)) }
 
 			open_set.push(node);
 		}
 	};
 
-	if solver.flag.debug == false { info.bar.unwrap().finish() }
-
 	let mut solution = Solution::new(open_set.len(), closed_set.len());
-	solution.path = vec![State { map: solver.goal, movement: last_move }];
+	let (_, map) = best;
+	solution.path = path_to(&closed_set, map, solver)?;
+	solution.moves = solution.path.len() - 1;
+	solution.cost = solver.path_cost(&solution.path);
+	solution.tie = Some(solver.tie);
+	if let Some(tree) = tree { tree.highlight(&solution.path) }
+	if !solver.flag.debug { solver.notify(Event::Solution { moves: solution.moves }) }
+	Ok(solution)
+}
+
+// Follow the moves of the closed set back to the start state, in the order of Solution
+fn path_to(closed_set: &ZobristMap<(Move, Position)>, map: Map, solver: &Solver) -> Result<Vec<State>, Error>
+{
+	let mut path = vec![];
+	let mut map = map;
 	loop
 	{
-		let last = solution.path.last().unwrap();
-		if last.movement == Move::No { break }
-		let map = last.movement.opposite().do_move(last.map.clone(), &last_pos, solver.size);
-		let movement = closed_set.remove(&map);
-		if movement.is_none()
+		let (movement, pos) = match closed_set.get(&Key::of(&map, solver.confirm))
 		{
-			return Err("found a solution but was unable to reconstruct the path".to_owned());
+			Some(entry) if path.len() <= closed_set.len() => entry.clone(),
+			_ => return Err(Error::Internal("found a state but was unable to reconstruct its path".to_owned()))
+		};
+		path.push(State { map: map.clone(), movement: movement.clone() });
+		if movement == Move::No { return Ok(path) }
+		map = movement.opposite().do_move(map, &pos, solver.size);
+	}
+}
+
+#[cfg(test)]
+mod tests
+{
+	use std::time::Instant;
+	use crate::Flag;
+	use crate::error::Error;
+	use crate::solver::Solver;
+	use crate::generator::Generator;
+	use crate::table::Table;
+
+	// Searches run out of states instead of panicking when the goal can't be reached
+	#[test]
+	fn exhausted()
+	{
+		let start = vec![2, 1, 3, 8, 0, 4, 7, 6, 5];
+		let solver = |greedy| Solver::new(Generator::snail(3), 3, "manhattan", Flag { greedy, ..Flag::default() }, Instant::now());
+		assert!(solver(false).is_solvable(&start).is_err());
+		assert!(matches!(super::find_path(start.clone(), &solver(false)), Err(Error::Exhausted(_))));
+		assert!(matches!(super::find_path(start, &solver(true)), Err(Error::Exhausted(_))));
+	}
+
+	// A state pushed again through a longer path once it is closed doesn't replace the path it was closed with
+	#[test]
+	fn closed_path()
+	{
+		let goal = Generator::snail(3);
+		let solver = Solver::new(goal.clone(), 3, "manhattan", Flag::default(), Instant::now());
+		for start in [vec![8, 6, 2, 5, 0, 7, 3, 4, 1], vec![7, 1, 0, 2, 4, 3, 8, 5, 6], vec![6, 7, 5, 3, 1, 0, 8, 4, 2]]
+		{
+			let solution = super::find_path(start.clone(), &solver).unwrap();
+			assert_eq!(Some(solution.moves), Table::new(&goal).distance(&start));
+			assert_eq!(solution.path.last().unwrap().map, start);
 		}
-		let movement = movement.unwrap();
-		last_pos = last_pos.update(&last.movement.opposite());
-		solution.path.push(State {map: map, movement: movement });
 	}
-	solution.moves = solution.path.len() - 1;
-	solution.display(solver.size, solver.flag.verbosity, solver.time);
-	Ok(())
-}
\ No newline at end of file
+}
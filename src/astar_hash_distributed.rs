@@ -0,0 +1,322 @@
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::mpsc::{channel, Sender, Receiver, RecvTimeoutError};
+use std::time::Duration;
+use std::thread;
+
+use crate::{Map, Move, Position};
+use crate::node::Node;
+use crate::solver::Solver;
+use crate::error::Error;
+use crate::observer::Event;
+use crate::display::{Solution, State, Interrupted};
+use crate::zobrist::{Key, ZobristMap};
+use crate::open_list::OpenList;
+
+// How long an idle thread waits for incoming nodes before checking for termination
+const IDLE_WAIT: Duration = Duration::from_millis(1);
+
+// Shared counters used by every thread of the search
+struct Shared
+{
+	// Cost of the best solution found so far
+	incumbent: AtomicUsize,
+	// Number of busy threads plus number of nodes sent but not yet received.
+	// When it drops to 0 no thread can ever generate a node again.
+	pending: AtomicUsize
+}
+
+// Part of the search space owned by a single thread
+struct Partition
+{
+	open_set: OpenList,
+	// Cost of each state with the move leading to it and the position of the blank that moved
+	closed_set: ZobristMap<(usize, Move, Position)>,
+	// Expanded node with the lowest h, reported if the search is cancelled
+	best: Option<(usize, Map)>,
+	// Cheapest state matching the goal, which may differ from it on the wildcards of a partial goal
+	goal: Option<(usize, Map)>
+}
+
+// The Zobrist hash of a state is already evenly spread, its remainder picks the thread owning it
+fn owner(hash: u64, threads: usize) -> usize
+{
+	(hash % threads as u64) as usize
+}
+
+impl Partition
+{
+	fn new(solver: &Solver) -> Self
+	{
+		Self { open_set: OpenList::new(solver.queue, solver.tie), closed_set: ZobristMap::default(), best: None, goal: None }
+	}
+
+	// Keep a received node only if it reaches its state with a lower g cost than before
+	fn receive(&mut self, node: Node, solver: &Solver)
+	{
+		if let Some((g, _, _)) = self.closed_set.get(&Key::new(&node, solver.confirm))
+		{
+			if *g <= node.g { return }
+		}
+		self.open_set.push(node);
+	}
+
+	fn has_work(&self, incumbent: usize) -> bool
+	{
+		match self.open_set.peek()
+		{
+			Some(node) => node.f < incumbent,
+			None => false
+		}
+	}
+
+	fn expand(&mut self, senders: &[Sender<Node>], shared: &Shared, id: usize, solver: &Solver)
+	{
+		let current = self.open_set.pop().unwrap();
+		let key = Key::new(&current, solver.confirm);
+		if let Some((g, _, _)) = self.closed_set.get(&key)
+		{
+			if *g <= current.g { return }
+		}
+		self.closed_set.insert(key, (current.g, current.movement.clone(), current.pos.clone()));
+		if self.best.as_ref().is_none_or(|(h, _)| current.h < *h)
+		{
+			self.best = Some((current.h, current.map.clone()));
+			solver.notify(Event::Best { h: current.h });
+		}
+
+		// If a solution is found, keep searching until no cheaper one can exist
+		if solver.is_goal(&current)
+		{
+			if self.goal.as_ref().is_none_or(|(g, _)| current.g < *g) { self.goal = Some((current.g, current.map.clone())) }
+			shared.incumbent.fetch_min(current.g, Ordering::SeqCst);
+			return
+		}
+
+		for mut node in current.generate_moves(solver.size, &solver.rules)
+		{
+			node = solver.update_cost(node);
+			let target = owner(node.hash, senders.len());
+			if target == id { self.receive(node, solver) }
+			else
+			{
+				shared.pending.fetch_add(1, Ordering::SeqCst);
+				// A receiver only hangs up once the search is cancelled
+				if senders[target].send(node).is_err() { return }
+			}
+		}
+	}
+
+	fn run(&mut self, receiver: Receiver<Node>, senders: Vec<Sender<Node>>, shared: &Shared, id: usize, solver: &Solver)
+	{
+		let mut busy = true;
+		loop
+		{
+			if solver.cancelled() { return }
+			// Collect the nodes sent by the other threads
+			loop
+			{
+				let node = match busy
+				{
+					true => match receiver.try_recv()
+					{
+						Ok(node) => node,
+						Err(_) => break
+					},
+					false => match receiver.recv_timeout(IDLE_WAIT)
+					{
+						Ok(node) => node,
+						Err(RecvTimeoutError::Timeout) if shared.pending.load(Ordering::SeqCst) == 0 => return,
+						Err(RecvTimeoutError::Timeout) if solver.cancelled() => return,
+						Err(_) => continue
+					}
+				};
+				// The count of an incoming node is handed over to the thread if it was idle
+				match busy
+				{
+					true => { shared.pending.fetch_sub(1, Ordering::SeqCst); },
+					false => busy = true
+				};
+				self.receive(node, solver);
+			}
+
+			if self.has_work(shared.incumbent.load(Ordering::SeqCst))
+			{
+				self.expand(&senders, shared, id, solver);
+			}
+			else if busy
+			{
+				busy = false;
+				shared.pending.fetch_sub(1, Ordering::SeqCst);
+			}
+		}
+	}
+}
+
+// Hash Distributed A*: every thread owns the states whose hash falls in its partition,
+// with its own open and closed sets, and sends the nodes it generates to their owner.
+pub fn find_path(start: Map, solver: &Solver) -> Result<Solution, Error>
+{
+	let threads = solver.threads;
+	let mut start = Node::new(start);
+	start.find_position(solver.size);
+	start = solver.get_cost(start);
+	let (start_h, start_map) = (start.h, start.map.clone());
+	solver.notify(Event::Started { h: start_h });
+
+	let shared = Shared
+	{
+		incumbent: AtomicUsize::new(usize::MAX),
+		pending: AtomicUsize::new(threads)
+	};
+	let (senders, receivers): (Vec<Sender<Node>>, Vec<Receiver<Node>>) = (0..threads).map(|_| channel()).unzip();
+	let mut partitions: Vec<Partition> = (0..threads).map(|_| Partition::new(solver)).collect();
+	partitions[owner(start.hash, threads)].open_set.push(start);
+
+	thread::scope(|scope|
+	{
+		for (id, (partition, receiver)) in partitions.iter_mut().zip(receivers).enumerate()
+		{
+			let senders = senders.clone();
+			let shared = &shared;
+			scope.spawn(move || partition.run(receiver, senders, shared, id, solver));
+		}
+	});
+
+	let open_size = partitions.iter().map(|p| p.open_set.len()).sum();
+	let closed_size = partitions.iter().map(|p| p.closed_set.len()).sum();
+	solver.notify(Event::Memory(vec![("open states", open_size), ("closed states", closed_size), ("total states", open_size + closed_size)]));
+
+	if solver.cancelled()
+	{
+		// There is no global bound, the lowest f left in the open sets is the closest thing
+		let bound = partitions.iter().filter_map(|p| p.open_set.peek()).map(|node| node.f).min().unwrap_or(0);
+		let (best_h, map) = match partitions.iter().filter_map(|p| p.best.clone()).min_by_key(|(h, _)| *h)
+		{
+			Some(best) => best,
+			None => (start_h, start_map)
+		};
+		let key = Key::of(&map, solver.confirm);
+		let path = match partitions[owner(key.hash, threads)].closed_set.contains_key(&key)
+		{
+			true => path_to(&partitions, map, solver)?,
+			false => vec![State { map, movement: Move::No }]
+		};
+		let stats = vec![("Number of pending states (open set)", open_size), ("Number of selected states (closed set)", closed_size)];
+		return Err(Error::Interrupted(Box::new(Interrupted { path, best_h, bound, stats })))
+	}
+	let goal = match partitions.iter().filter_map(|p| p.goal.clone()).min_by_key(|(g, _)| *g)
+	{
+		Some((_, goal)) => goal,
+		None => return Err(Error::Exhausted("no state reachable from the start is the goal".to_owned()))
+	};
+
+	let mut solution = Solution::new(open_size, closed_size);
+	solution.path = path_to(&partitions, goal, solver)?;
+	solution.moves = solution.path.len() - 1;
+	solution.cost = solver.path_cost(&solution.path);
+	solution.tie = Some(solver.tie);
+	solver.notify(Event::Solution { moves: solution.moves });
+	Ok(solution)
+}
+
+// Follow the moves back to the start state through the partitions owning each state
+fn path_to(partitions: &[Partition], mut map: Map, solver: &Solver) -> Result<Vec<State>, Error>
+{
+	let closed_size: usize = partitions.iter().map(|p| p.closed_set.len()).sum();
+	let mut path = vec![];
+	loop
+	{
+		let key = Key::of(&map, solver.confirm);
+		let (movement, pos) = match partitions[owner(key.hash, partitions.len())].closed_set.get(&key)
+		{
+			Some((_, movement, pos)) if path.len() <= closed_size => (movement.clone(), pos.clone()),
+			_ => return Err(Error::Internal("found a state but was unable to reconstruct its path".to_owned()))
+		};
+		path.push(State { map: map.clone(), movement: movement.clone() });
+		if movement == Move::No { return Ok(path) }
+		map = movement.opposite().do_move(map, &pos, solver.size);
+	}
+}
+
+pub fn solve(start: Map, mut solver: Solver) -> Result<(), Error>
+{
+	solver.flag.greedy = false;
+	let mut solution = find_path(start, &solver)?;
+	solution.display(solver.size, solver.flag.verbosity, solver.time);
+	Ok(())
+}
+
+#[cfg(test)]
+mod tests
+{
+	use std::thread;
+	use std::time::{Duration, Instant};
+	use std::sync::atomic::Ordering;
+	use crate::Flag;
+	use crate::error::Error;
+	use crate::node::Node;
+	use crate::solver::Solver;
+	use crate::generator::Generator;
+	use crate::astar_iterative_deepening;
+
+	fn optimal_moves(start: &[usize], solver: &Solver) -> usize
+	{
+		let mut node = Node::new(start.to_vec());
+		node.find_position(solver.size);
+		node = solver.get_cost(node);
+		let mut bound = node.h;
+		loop
+		{
+			let result = astar_iterative_deepening::find_path(&node, bound, solver);
+			if let Some(path) = result.path { return path.len() }
+			bound = result.bound;
+		}
+	}
+
+	fn compare(start: Vec<usize>, size: usize, threads: usize)
+	{
+		let flag = Flag { verbosity: false, debug: false, greedy: false, uniform: false };
+		let mut solver = Solver::new(Generator::snail(size), size, "manhattan", flag, Instant::now());
+		solver.threads = threads;
+		let solution = super::find_path(start.clone(), &solver).unwrap();
+		assert_eq!(solution.moves, optimal_moves(&start, &solver));
+		assert_eq!(solution.path.last().unwrap().map, start);
+		assert_eq!(solution.path[0].map, Generator::snail(size));
+	}
+
+	#[test]
+	fn optimal_3x3()
+	{
+		compare(vec![5, 4, 3, 1, 0, 2, 6, 7, 8], 3, 1);
+		compare(vec![5, 4, 3, 1, 0, 2, 6, 7, 8], 3, 4);
+		compare(vec![6, 7, 1, 5, 2, 3, 4, 8, 0], 3, 3);
+	}
+
+	#[test]
+	fn optimal_4x4()
+	{
+		compare(vec![13, 1, 3, 4, 0, 2, 9, 5, 8, 14, 6, 7, 12, 11, 10, 15], 4, 4);
+	}
+
+	// A cancelled search reports a valid path from the start to its best state
+	#[test]
+	fn interrupted()
+	{
+		let start = vec![0, 12, 9, 13, 15, 11, 10, 14, 3, 7, 2, 5, 4, 8, 6, 1];
+		let mut solver = Solver::new(Generator::snail(4), 4, "manhattan", Flag::default(), Instant::now());
+		solver.threads = 2;
+		let cancel = solver.cancel.clone();
+		thread::spawn(move || { thread::sleep(Duration::from_millis(200)); cancel.store(true, Ordering::Relaxed) });
+		let progress = match super::find_path(start.clone(), &solver)
+		{
+			Err(Error::Interrupted(progress)) => progress,
+			_ => panic!("the search should have been interrupted")
+		};
+		assert_eq!(progress.path.last().unwrap().map, start);
+		for step in progress.path.windows(2)
+		{
+			assert_eq!(step[0].map.iter().zip(&step[1].map).filter(|(a, b)| a != b).count(), 2);
+		}
+		assert!(progress.best_h < solver.get_cost({ let mut node = Node::new(start); node.find_position(4); node }).h);
+	}
+}
@@ -1,81 +1,540 @@
-use crate::Map;
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::time::{Duration, Instant};
+use crate::{Map, Move};
 use crate::node::Node;
 use crate::solver::Solver;
-use crate::display::{Info, Solution, State};
+use crate::error::Error;
+use crate::observer::Event;
+use crate::display::{Solution, Solutions, State, Interrupted};
+use crate::astar_iterative_deepening_parallel;
+use crate::checkpoint::Checkpoint;
+
+// Maximum number of nodes expanded in a single iteration before giving up
+pub const MAX_EXPANDED: usize = 10000000;
 
 pub struct Results
 {
     pub path: Option<Vec<State>>,
     pub bound: usize,
     pub expanded: usize,
-    pub best_h: usize
+    pub best_h: usize,
+    // Path to the state with the lowest h below the node searched, in the order of Solution
+    pub best: Vec<State>
 }
 
-pub fn solve(start: Map, mut solver: Solver) -> Result<(), String>
+pub fn solve(start: Map, mut solver: Solver) -> Result<(), Error>
 {
     solver.flag.greedy = false;
 	let mut start = Node::new(start);
 	start.find_position(solver.size);
 	start = solver.get_cost(start);
 
-    let mut info = Info::new(start.h);
-    let max_bound = <usize>::max_value();
+    let (result, max_expanded, total_expanded) = first_solution(&start, &solver)?;
+    let mut solution = Solution::new(max_expanded, total_expanded);
+    solution.path = result.path.unwrap();
+    solution.path.push(State { map: start.map, movement: Move::No });
+    solution.moves = solution.path.len() - 1;
+    solution.cost = solver.path_cost(&solution.path);
+    solver.notify(Event::Solution { moves: solution.moves });
+    solution.display_ida(solver.size, solver.flag.verbosity, solver.time);
+    Ok(())
+}
+
+// Raise the bound until a first solution is found, with the maximum and total numbers of states expanded
+fn first_solution(start: &Node, solver: &Solver) -> Result<(Results, usize, usize), Error>
+{
+    solver.notify(Event::Started { h: start.h });
+    let max_bound = usize::MAX;
 	let mut bound = start.h;
     let mut total_expanded = 0;
     let mut max_expanded = 0;
-    let result = loop
+    let mut best = (start.h, vec![]);
+    let mut iteration = 1;
+    loop
     {
-        let res = find_path(&start, 0, bound, &solver);
+        solver.notify(Event::Iteration { number: iteration, bound });
+        let res = match solver.threads > 1
+        {
+            true => astar_iterative_deepening_parallel::find_path(start, bound, solver),
+            false => find_path(start, bound, solver)
+        };
         if res.expanded > max_expanded { max_expanded = res.expanded }
         total_expanded += res.expanded;
-        info.update_ida(res.best_h, max_expanded, total_expanded);
-        if res.path.is_some() { break res }
-        if res.bound == max_bound { return Err("solution not found".to_owned()) }
+        solver.notify(Event::Best { h: res.best_h });
+        solver.notify(Event::Memory(vec![("max states", max_expanded), ("total states", total_expanded)]));
+        if res.path.is_some() { return Ok((res, max_expanded, total_expanded)) }
+        if res.best_h < best.0 { best = (res.best_h, res.best) }
+        if solver.cancelled()
+        {
+            let (best_h, mut path) = best;
+            path.push(State { map: start.map.clone(), movement: Move::No });
+            let stats = vec![("Maximum number of states expanded", max_expanded), ("Total number of states ever expanded", total_expanded)];
+            return Err(Error::Interrupted(Box::new(Interrupted { path, best_h, bound, stats })))
+        }
+        if res.bound == max_bound && res.expanded > MAX_EXPANDED
+        {
+            return Err(Error::Limit(format!("search expanded more than {} nodes in a single iteration", MAX_EXPANDED)))
+        }
+        if res.bound == max_bound { return Err(Error::Exhausted("no state reachable from the start is the goal".to_owned())) }
+        solver.notify(Event::Bound { bound: res.bound });
         bound = res.bound;
-    };
+        iteration += 1;
+    }
+}
 
-    info.bar.unwrap().finish();
-    let mut solution = Solution::new(max_expanded, total_expanded);
-    solution.path = result.path.unwrap();
-    solution.moves = solution.path.len() - 1;
-    solution.display_ida(solver.size, solver.flag.verbosity, solver.time);
+// Count the optimal solutions once the first one gives their cost, and list the first 'list' of them.
+// Counting shares the paths going through the same state at the same cost, so large totals stay cheap.
+pub fn solve_all(start: Map, mut solver: Solver, list: usize) -> Result<(), Error>
+{
+    solver.flag.greedy = false;
+	let mut start = Node::new(start);
+	start.find_position(solver.size);
+	start = solver.get_cost(start);
+
+    let (result, _, _) = first_solution(&start, &solver)?;
+    let bound = result.bound;
+    let mut first = result.path.unwrap();
+    first.push(State { map: start.map.clone(), movement: Move::No });
+    let mut solutions = Solutions::new(first.len() - 1, solver.path_cost(&first));
+    solver.notify(Event::Solution { moves: solutions.moves });
+
+    solutions.count = count_paths(&start, bound, &solver, &mut HashMap::new());
+    if list > 0 { find_all(&start, bound, &solver, list, &mut vec![], &mut solutions.paths) }
+    if solver.cancelled()
+    {
+        let stats = vec![("Number of optimal solutions listed", solutions.paths.len())];
+        return Err(Error::Interrupted(Box::new(Interrupted { path: first, best_h: 0, bound, stats })))
+    }
+    for path in solutions.paths.iter_mut()
+    {
+        path.push(State { map: start.map.clone(), movement: Move::No });
+    }
+    solutions.display(solver.size, solver.flag.verbosity, solver.time);
     Ok(())
 }
 
-pub fn find_path(current: &Node, g_cost: usize, bound: usize, solver: &Solver) -> Results
+// Number of paths from the node to the goal whose f never exceeds the bound, which are the optimal
+// solutions when the bound is their cost. A state is counted once for each cost it is reached with.
+pub fn count_paths(current: &Node, bound: usize, solver: &Solver, memo: &mut HashMap<(Map, usize), u128>) -> u128
+{
+    if current.f > bound || solver.cancelled() { return 0 }
+    if solver.is_goal(current) { return 1 }
+    if let Some(count) = memo.get(&(current.map.clone(), current.g)) { return *count }
+
+    let count = current.generate_moves(solver.size, &solver.rules)
+        .into_iter()
+        .map(|node| count_paths(&solver.update_cost(node), bound, solver, memo))
+        .sum();
+    memo.insert((current.map.clone(), current.g), count);
+    count
+}
+
+// Collect the paths counted by count_paths in the order of Solution and without the node, until 'limit' of them are found
+pub fn find_all(current: &Node, bound: usize, solver: &Solver, limit: usize, prefix: &mut Vec<State>, paths: &mut Vec<Vec<State>>)
 {
-    let mut next_bound = <usize>::max_value();
+    if current.f > bound || solver.cancelled() { return }
+    if solver.is_goal(current)
+    {
+        paths.push(prefix.iter().rev().cloned().collect());
+        return
+    }
+    for node in current.generate_moves(solver.size, &solver.rules)
+    {
+        if paths.len() >= limit { return }
+        let node = solver.update_cost(node);
+        prefix.push(State { map: node.map.clone(), movement: node.movement.clone() });
+        find_all(&node, bound, solver, limit, prefix, paths);
+        prefix.pop();
+    }
+}
+
+// Returns the number of moves of an optimal solution or None if the search gave up
+pub fn optimal_length(start: Map, solver: &Solver) -> Option<usize>
+{
+	let mut start = Node::new(start);
+	start.find_position(solver.size);
+	start = solver.get_cost(start);
+
+	let mut bound = start.h;
+	loop
+	{
+		let result = find_path(&start, bound, solver);
+		if let Some(path) = result.path { return Some(path.len()) }
+		if result.bound == usize::MAX { return None }
+		bound = result.bound;
+	}
+}
+
+pub fn find_path(current: &Node, bound: usize, solver: &Solver) -> Results
+{
+    find_path_until(current, bound, solver, &AtomicBool::new(false))
+}
+
+// Same as find_path but gives up as soon as 'stop' is raised by another thread or the search is cancelled
+pub fn find_path_until(current: &Node, bound: usize, solver: &Solver, stop: &AtomicBool) -> Results
+{
+    let mut next_bound = usize::MAX;
     let mut best_h = current.h;
-    
+    let mut best = vec![];
+
     let mut expanded = 0;
     if current.f > bound
     {
-        return Results {path: None, bound: current.f, expanded: expanded, best_h: best_h };
+        return Results {path: None, bound: current.f, expanded, best_h, best };
     }
-    if current.h == 0
+    if solver.is_goal(current)
     {
-        return Results { path: Some(vec![]), bound: current.f, expanded: expanded + 1, best_h: 0 }
+        return Results { path: Some(vec![]), bound: current.f, expanded: expanded + 1, best_h: 0, best }
     }
 
-    let moves: Vec<Node> = current.generate_moves(solver.size);
+    let moves: Vec<Node> = current.generate_moves(solver.size, &solver.rules);
     expanded += 1;
     for mut node in moves
     {
+        if stop.load(Ordering::Relaxed) || solver.cancelled()
+        {
+            return Results { path: None, bound: usize::MAX, expanded, best_h, best }
+        }
         node = solver.update_cost(node);
-        let result = find_path(&node, g_cost + 1, bound, solver);
+        let result = find_path_until(&node, bound, solver, stop);
         expanded += result.expanded;
-        if expanded > 10000000
+        if result.best_h < best_h
+        {
+            best_h = result.best_h;
+            best = result.best;
+            best.push(State { map: node.map.clone(), movement: node.movement.clone() });
+        }
+        if expanded > MAX_EXPANDED
         {
-            return Results {path: None, bound: <usize>::max_value(), expanded, best_h: node.h }
+            return Results {path: None, bound: usize::MAX, expanded, best_h, best }
         }
-        if result.path.is_some()
+        if let Some(mut path) = result.path
         {
-           let mut path = result.path.unwrap();
            path.push(State {map: node.map, movement: node.movement });
-           return Results { path: Some(path), bound: bound, expanded: expanded, best_h: 0 };
+           return Results { path: Some(path), bound, expanded, best_h: 0, best: vec![] };
         }
         if result.bound < next_bound { next_bound = result.bound }
-        if result.best_h < best_h { best_h = result.best_h }
     }
-    Results { path: None, bound: next_bound, expanded: expanded, best_h: best_h }
-}
\ No newline at end of file
+    Results { path: None, bound: next_bound, expanded, best_h, best }
+}
+
+// Progress of an iteration of IDA* that can be saved and resumed, its position in the traversal being
+// the index of the child followed at each depth
+pub struct Trail<'a>
+{
+    checkpoint: Checkpoint,
+    filepath: Option<&'a str>,
+    interval: Duration,
+    saved: Instant,
+    indexes: Vec<usize>,
+    // Position the iteration starts again from, forgotten once the traversal goes past it
+    resume: Option<Vec<usize>>,
+    // States from the start to the current node, in the order of the moves
+    path: Vec<State>,
+    best: (usize, Vec<State>),
+    next_bound: usize,
+    expanded: usize,
+    stopped: bool,
+    error: Option<Error>
+}
+
+impl<'a> Trail<'a>
+{
+    pub fn new(checkpoint: Checkpoint, h: usize, filepath: Option<&'a str>, interval: Duration) -> Self
+    {
+        Self
+        {
+            resume: Some(checkpoint.position.clone()),
+            next_bound: checkpoint.next_bound,
+            expanded: checkpoint.expanded,
+            checkpoint,
+            filepath,
+            interval,
+            saved: Instant::now(),
+            indexes: vec![],
+            path: vec![],
+            best: (h, vec![]),
+            stopped: false,
+            error: None
+        }
+    }
+
+    // Where the iteration is now
+    pub fn checkpoint(&self) -> Checkpoint
+    {
+        let mut checkpoint = self.checkpoint.clone();
+        // Until the traversal reaches the position it resumes from, it is still there
+        checkpoint.position = self.resume.as_ref().unwrap_or(&self.indexes).clone();
+        checkpoint.next_bound = self.next_bound;
+        checkpoint.expanded = self.expanded;
+        checkpoint
+    }
+
+    fn save(&mut self)
+    {
+        if let Some(filepath) = self.filepath
+        {
+            if let Err(e) = self.checkpoint().create_file(filepath)
+            {
+                self.error = Some(e);
+                self.stopped = true;
+            }
+        }
+        self.saved = Instant::now();
+    }
+
+    // Save the position when it is time to or when the search is cancelled, false once the search must stop
+    #[allow(clippy::manual_is_multiple_of)]
+    fn tick(&mut self, solver: &Solver) -> bool
+    {
+        if self.stopped { return false }
+        if solver.cancelled()
+        {
+            self.save();
+            self.stopped = true;
+        }
+        else if self.expanded % 1024 == 0 && self.saved.elapsed() >= self.interval
+        {
+            self.save();
+        }
+        !self.stopped
+    }
+}
+
+pub fn solve_resumable(start: Map, mut solver: Solver, filepath: Option<String>, interval: Duration, resume: Option<Checkpoint>) -> Result<(), Error>
+{
+    solver.flag.greedy = false;
+    let mut solution = find_path_checkpointed(start, &solver, filepath.as_deref(), interval, resume)?;
+    if let Some(filepath) = &filepath { Checkpoint::remove_file(filepath)? }
+    solution.display_ida(solver.size, solver.flag.verbosity, solver.time);
+    Ok(())
+}
+
+// IDA* writing checkpoints to 'filepath' every 'interval' and starting from 'resume' if given.
+// Iterations aren't limited to MAX_EXPANDED states since such searches are meant to run for hours.
+pub fn find_path_checkpointed(start: Map, solver: &Solver, filepath: Option<&str>, interval: Duration, resume: Option<Checkpoint>) -> Result<Solution, Error>
+{
+    let mut start = Node::new(start);
+    start.find_position(solver.size);
+    start = solver.get_cost(start);
+
+    let mut checkpoint = Checkpoint::new(&start.map, start.h, solver);
+    if let Some(resume) = resume
+    {
+        resume.check(&checkpoint)?;
+        checkpoint = resume;
+    }
+    solver.notify(Event::Started { h: start.h });
+    let path = loop
+    {
+        solver.notify(Event::Iteration { number: checkpoint.iteration, bound: checkpoint.bound });
+        let mut trail = Trail::new(checkpoint, start.h, filepath, interval);
+        trail.save();
+        let path = find_path_resumable(&start, trail.checkpoint.bound, solver, &mut trail);
+        if let Some(error) = trail.error { return Err(error) }
+
+        checkpoint = trail.checkpoint();
+        let (max_expanded, total_expanded) = (checkpoint.max_expanded.max(checkpoint.expanded), checkpoint.total_expanded + checkpoint.expanded);
+        solver.notify(Event::Best { h: trail.best.0 });
+        solver.notify(Event::Memory(vec![("max states", max_expanded), ("total states", total_expanded)]));
+        if let Some(path) = path { break path }
+        if solver.cancelled()
+        {
+            let (best_h, mut path) = trail.best;
+            path.reverse();
+            path.push(State { map: start.map, movement: Move::No });
+            let stats = vec![("Maximum number of states expanded", max_expanded), ("Total number of states ever expanded", total_expanded)];
+            return Err(Error::Interrupted(Box::new(Interrupted { path, best_h, bound: checkpoint.bound, stats })))
+        }
+        if checkpoint.next_bound == usize::MAX { return Err(Error::Exhausted("no state reachable from the start is the goal".to_owned())) }
+
+        solver.notify(Event::Bound { bound: checkpoint.next_bound });
+        checkpoint.bound = checkpoint.next_bound;
+        checkpoint.iteration += 1;
+        checkpoint.position = vec![];
+        checkpoint.next_bound = usize::MAX;
+        checkpoint.max_expanded = max_expanded;
+        checkpoint.total_expanded = total_expanded;
+        checkpoint.expanded = 0;
+    };
+
+    let mut solution = Solution::new(checkpoint.max_expanded.max(checkpoint.expanded), checkpoint.total_expanded + checkpoint.expanded);
+    solution.path = path;
+    solution.path.push(State { map: start.map, movement: Move::No });
+    solution.moves = solution.path.len() - 1;
+    solution.cost = solver.path_cost(&solution.path);
+    solver.notify(Event::Solution { moves: solution.moves });
+    Ok(solution)
+}
+
+// Same search as find_path, skipping the children before the position the trail resumes from
+pub fn find_path_resumable(current: &Node, bound: usize, solver: &Solver, trail: &mut Trail) -> Option<Vec<State>>
+{
+    if current.f > bound
+    {
+        trail.next_bound = trail.next_bound.min(current.f);
+        return None
+    }
+    if current.h < trail.best.0 { trail.best = (current.h, trail.path.clone()) }
+    let depth = trail.indexes.len();
+    let first = match &trail.resume
+    {
+        Some(resume) if depth < resume.len() => resume[depth],
+        _ => { trail.resume = None; 0 }
+    };
+    // The ancestors of the position resumed from were already counted before it was saved
+    if trail.resume.is_none() { trail.expanded += 1 }
+    if solver.is_goal(current) { return Some(vec![]) }
+
+    for (index, mut node) in current.generate_moves(solver.size, &solver.rules).into_iter().enumerate().skip(first)
+    {
+        trail.indexes.push(index);
+        if !trail.tick(solver) { return None }
+        node = solver.update_cost(node);
+        trail.path.push(State { map: node.map.clone(), movement: node.movement.clone() });
+        let found = find_path_resumable(&node, bound, solver, trail);
+        if trail.stopped { return None }
+        let state = trail.path.pop().unwrap();
+        trail.indexes.pop();
+        trail.resume = None;
+        if let Some(mut path) = found
+        {
+            path.push(state);
+            return Some(path)
+        }
+    }
+    None
+}
+
+#[cfg(test)]
+mod tests
+{
+	use std::collections::{HashMap, HashSet, VecDeque};
+	use std::sync::Arc;
+	use std::sync::atomic::{AtomicBool, Ordering};
+	use std::time::{Duration, Instant};
+	use std::fs;
+	use crate::{Flag, Move};
+	use crate::observer::{Event, Observer, Silent};
+	use crate::node::Node;
+	use crate::solver::Solver;
+	use crate::generator::Generator;
+	use crate::checkpoint::Checkpoint;
+	use crate::error::Error;
+
+	// Number of shortest paths from the start to each state, layer by layer
+	fn breadth_first_count(start: &[usize], goal: &[usize], size: usize, solver: &Solver) -> (usize, u128)
+	{
+		let mut depth: HashMap<Vec<usize>, (usize, u128)> = HashMap::new();
+		let mut queue = VecDeque::new();
+		let mut node = Node::new(start.to_vec());
+		node.find_position(size);
+		depth.insert(start.to_vec(), (0, 1));
+		queue.push_back(node);
+		while let Some(node) = queue.pop_front()
+		{
+			let (d, paths) = depth[&node.map];
+			if node.map == goal { return (d, paths) }
+			for child in node.generate_moves(size, &solver.rules)
+			{
+				match depth.get_mut(&child.map)
+				{
+					Some((cd, count)) if *cd == d + 1 => *count += paths,
+					Some(_) => (),
+					None => { depth.insert(child.map.clone(), (d + 1, paths)); queue.push_back(child) }
+				}
+			}
+		}
+		unreachable!()
+	}
+
+	#[test]
+	fn optimal_solutions()
+	{
+		let goal = Generator::snail(3);
+		let solver = Solver::new(goal.clone(), 3, "manhattan", Flag::default(), Instant::now());
+		for start in [vec![5, 4, 3, 1, 0, 2, 6, 7, 8], vec![6, 7, 1, 5, 2, 3, 4, 8, 0], vec![1, 2, 3, 0, 8, 4, 7, 6, 5]]
+		{
+			let (moves, expected) = breadth_first_count(&start, &goal, 3, &solver);
+			let mut node = Node::new(start.clone());
+			node.find_position(3);
+			node = solver.get_cost(node);
+
+			assert_eq!(super::count_paths(&node, moves, &solver, &mut HashMap::new()), expected);
+			let mut paths = vec![];
+			super::find_all(&node, moves, &solver, usize::MAX, &mut vec![], &mut paths);
+			assert_eq!(paths.len() as u128, expected);
+			assert!(paths.iter().all(|path| path.len() == moves && path[0].map == goal));
+			let distinct: HashSet<Vec<Vec<usize>>> = paths.iter().map(|path| path.iter().map(|state| state.map.clone()).collect()).collect();
+			assert_eq!(distinct.len() as u128, expected);
+
+			let mut first = vec![];
+			super::find_all(&node, moves, &solver, 1, &mut vec![], &mut first);
+			assert_eq!(first.len(), 1);
+		}
+	}
+
+	// Cancels the search as soon as it starts the iteration after the one it resumed
+	struct Canceller
+	{
+		cancel: Arc<AtomicBool>,
+		iterations: usize
+	}
+
+	impl Observer for Canceller
+	{
+		fn notify(&mut self, event: Event)
+		{
+			if let Event::Iteration { .. } = event
+			{
+				self.iterations += 1;
+				if self.iterations == 2 { self.cancel.store(true, Ordering::Relaxed) }
+			}
+		}
+	}
+
+	// A search cancelled again and again ends with the same solution when resumed from its checkpoints
+	#[test]
+	fn checkpoints()
+	{
+		// Generated with an optimal solution of 32 moves
+		let start = vec![12, 5, 14, 4, 10, 2, 11, 6, 15, 0, 3, 7, 13, 1, 9, 8];
+		let solver = Solver::new(Generator::snail(4), 4, "manhattan", Flag::default(), Instant::now());
+		let filepath = std::env::temp_dir().join(format!("npuzzle_checkpoint_{}", std::process::id()));
+		let filepath = filepath.to_str().unwrap();
+
+		let mut resume = None;
+		let mut stops = 0;
+		let solution = loop
+		{
+			*solver.observer.lock().unwrap() = Box::new(Canceller { cancel: solver.cancel.clone(), iterations: 0 });
+			let result = super::find_path_checkpointed(start.clone(), &solver, Some(filepath), Duration::from_secs(0), resume);
+			solver.cancel.store(false, Ordering::Relaxed);
+			match result
+			{
+				Ok(solution) => break solution,
+				Err(Error::Interrupted(_)) => stops += 1,
+				Err(error) => panic!("{}", error)
+			};
+			resume = Some(Checkpoint::from_file(filepath).unwrap());
+		};
+		fs::remove_file(filepath).unwrap();
+
+		assert_eq!(solution.moves, 32);
+		// Each run goes through a single iteration, the bound rising by 2 from the heuristic of the start
+		let mut node = Node::new(start.clone());
+		node.find_position(4);
+		assert_eq!(stops, (32 - solver.get_cost(node).h) / 2);
+		// Resuming expands each state once, as a search that is never stopped
+		*solver.observer.lock().unwrap() = Box::new(Silent);
+		let whole = super::find_path_checkpointed(start.clone(), &solver, None, Duration::from_secs(3600), None).unwrap();
+		assert_eq!((solution.pending, solution.selected), (whole.pending, whole.selected));
+		assert_eq!(solution.path[0].map, Generator::snail(4));
+		assert_eq!(solution.path.last().unwrap(), &crate::display::State { map: start, movement: Move::No });
+		for step in solution.path.windows(2)
+		{
+			assert_eq!(step[0].map.iter().zip(&step[1].map).filter(|(a, b)| a != b).count(), 2);
+		}
+	}
+}
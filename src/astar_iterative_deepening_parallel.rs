@@ -0,0 +1,210 @@
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::sync::Mutex;
+use std::thread;
+
+use crate::node::Node;
+use crate::solver::Solver;
+use crate::display::State;
+use crate::astar_iterative_deepening::{find_path_until, Results, MAX_EXPANDED};
+
+// Number of frontier nodes wanted per thread so that the work stays balanced
+const NODES_PER_THREAD: usize = 16;
+
+// A node of the frontier with the states leading to it from the start node
+type Branch = (Node, Vec<State>);
+
+struct Split
+{
+	frontier: Vec<Branch>,
+	path: Option<Vec<State>>,
+	bound: usize,
+	expanded: usize,
+	best_h: usize,
+	// States from the start to the best node met while splitting
+	best: Vec<State>
+}
+
+// Expand the tree breadth first until there are enough nodes to share between threads.
+// Nodes above the bound or solutions met on the way are handled exactly like find_path does.
+fn split_tree(start: &Node, bound: usize, solver: &Solver) -> Split
+{
+	let wanted = solver.threads * NODES_PER_THREAD;
+	let mut split = Split { frontier: vec![(start.clone(), vec![])], path: None, bound: usize::MAX, expanded: 0, best_h: start.h, best: vec![] };
+
+	while !split.frontier.is_empty() && split.frontier.len() < wanted
+	{
+		let mut layer: Vec<Branch> = vec![];
+		for (node, prefix) in std::mem::take(&mut split.frontier)
+		{
+			if node.h < split.best_h
+			{
+				split.best_h = node.h;
+				split.best = prefix.clone();
+			}
+			if node.f > bound
+			{
+				if node.f < split.bound { split.bound = node.f }
+				continue
+			}
+			if solver.is_goal(&node)
+			{
+				split.expanded += 1;
+				split.path = Some(prefix);
+				return split
+			}
+			split.expanded += 1;
+			for mut child in node.generate_moves(solver.size, &solver.rules)
+			{
+				child = solver.update_cost(child);
+				let mut states = prefix.clone();
+				states.push(State { map: child.map.clone(), movement: child.movement.clone() });
+				layer.push((child, states));
+			}
+		}
+		split.frontier = layer;
+	}
+	split
+}
+
+// Parallel version of astar_iterative_deepening::find_path for a single iteration.
+// The tree is split below a shallow frontier and every thread takes the next unexplored
+// branch until none are left or one of them finds a solution within the bound.
+pub fn find_path(start: &Node, bound: usize, solver: &Solver) -> Results
+{
+	let split = split_tree(start, bound, solver);
+	if let Some(prefix) = split.path
+	{
+		return Results { path: Some(prefix.into_iter().rev().collect()), bound, expanded: split.expanded, best_h: 0, best: vec![] }
+	}
+	if solver.cancelled()
+	{
+		return Results { path: None, bound: usize::MAX, expanded: split.expanded, best_h: split.best_h, best: split.best.into_iter().rev().collect() }
+	}
+
+	let next_branch = AtomicUsize::new(0);
+	let next_bound = AtomicUsize::new(split.bound);
+	let best: Mutex<(usize, Vec<State>)> = Mutex::new((split.best_h, split.best.iter().rev().cloned().collect()));
+	let expanded = AtomicUsize::new(split.expanded);
+	let found = AtomicBool::new(false);
+	let exhausted = AtomicBool::new(false);
+	let solution: Mutex<Option<Vec<State>>> = Mutex::new(None);
+
+	thread::scope(|scope|
+	{
+		for _ in 0..solver.threads
+		{
+			scope.spawn(||
+			{
+				while !found.load(Ordering::Relaxed) && !solver.cancelled()
+				{
+					let index = next_branch.fetch_add(1, Ordering::Relaxed);
+					if index >= split.frontier.len() { break }
+					let (node, prefix) = &split.frontier[index];
+
+					let result = find_path_until(node, bound, solver, &found);
+					if expanded.fetch_add(result.expanded, Ordering::Relaxed) + result.expanded > MAX_EXPANDED
+					{
+						exhausted.store(true, Ordering::Relaxed);
+						found.store(true, Ordering::Relaxed);
+						break
+					}
+					{
+						let mut best = best.lock().unwrap();
+						if result.best_h < best.0
+						{
+							let mut path = result.best;
+							path.extend(prefix.iter().rev().cloned());
+							*best = (result.best_h, path);
+						}
+					}
+					if let Some(mut path) = result.path
+					{
+						let mut solution = solution.lock().unwrap();
+						if solution.is_none()
+						{
+							path.extend(prefix.iter().rev().cloned());
+							*solution = Some(path);
+							found.store(true, Ordering::Relaxed);
+						}
+						break
+					}
+					next_bound.fetch_min(result.bound, Ordering::Relaxed);
+				}
+			});
+		}
+	});
+
+	let expanded = expanded.into_inner();
+	let (best_h, best) = best.into_inner().unwrap();
+	if exhausted.into_inner() || solver.cancelled()
+	{
+		return Results { path: None, bound: usize::MAX, expanded, best_h, best }
+	}
+	match solution.into_inner().unwrap()
+	{
+		Some(path) => Results { path: Some(path), bound, expanded, best_h: 0, best: vec![] },
+		None => Results { path: None, bound: next_bound.into_inner(), expanded, best_h, best }
+	}
+}
+
+#[cfg(test)]
+mod tests
+{
+	use std::time::Instant;
+	use crate::{Flag, Rules};
+	use crate::node::Node;
+	use crate::solver::Solver;
+	use crate::generator::Generator;
+	use crate::astar_iterative_deepening;
+
+	fn compare(start: Vec<usize>, size: usize, heuristic: &str, threads: usize)
+	{
+		let flag = Flag { verbosity: false, debug: false, greedy: false, uniform: false };
+		let mut solver = Solver::new(Generator::snail(size), size, heuristic, flag, Instant::now());
+		solver.threads = threads;
+		let mut node = Node::new(start);
+		node.find_position(size);
+		node = solver.get_cost(node);
+
+		let mut bound = node.h;
+		loop
+		{
+			let expected = astar_iterative_deepening::find_path(&node, bound, &solver);
+			let got = super::find_path(&node, bound, &solver);
+			assert_eq!(got.path.as_ref().map(|p| p.len()), expected.path.as_ref().map(|p| p.len()));
+			if let Some(path) = got.path
+			{
+				assert_eq!(path.last().unwrap().map, node.generate_moves(size, &Rules::default())
+					.into_iter()
+					.find(|n| n.movement == path.last().unwrap().movement)
+					.unwrap()
+					.map);
+				assert_eq!(path[0].map, Generator::snail(size));
+				break
+			}
+			assert_eq!(got.bound, expected.bound);
+			assert_eq!(got.best_h, expected.best_h);
+			bound = got.bound;
+		}
+	}
+
+	#[test]
+	fn same_as_sequential_3x3()
+	{
+		compare(vec![5, 4, 3, 1, 0, 2, 6, 7, 8], 3, "manhattan", 4);
+		compare(vec![6, 7, 1, 5, 2, 3, 4, 8, 0], 3, "manhattan", 8);
+	}
+
+	#[test]
+	fn same_as_sequential_4x4()
+	{
+		compare(vec![1, 2, 3, 4, 12, 13, 14, 5, 0, 11, 15, 6, 10, 9, 8, 7], 4, "conflict", 3);
+		compare(vec![13, 1, 3, 4, 0, 2, 9, 5, 8, 14, 6, 7, 12, 11, 10, 15], 4, "conflict", 4);
+	}
+
+	#[test]
+	fn solution_in_frontier()
+	{
+		compare(vec![1, 2, 3, 8, 4, 0, 7, 6, 5], 3, "manhattan", 2);
+	}
+}
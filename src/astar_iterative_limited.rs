@@ -1,22 +1,26 @@
-use std::collections::{HashSet, BinaryHeap};
 use colored::*;
 use crate::{Map, Move};
 use crate::node::Node;
 use crate::solver::Solver;
-use crate::display::{Info, Solution, State};
-
-pub fn solve(start: Map, solver: Solver) -> Result<(), String>
+use crate::error::Error;
+use crate::observer::Event;
+use crate::display::{Solution, Interrupted, path_from_moves};
+use crate::optimizer;
+use crate::open_list::OpenList;
+use crate::zobrist::{Key, ZobristSet};
+
+pub fn solve(start: Map, solver: Solver) -> Result<(), Error>
 {
 	let mut start = Node::new(start);
 	start.find_position(solver.size);
 	start = solver.get_cost(start);
-	start.move_list.push(Move::No);
+	start.move_list.push((Move::No, start.pos.clone()));
 
 	// Regulate number of nodes to explore for each iterations
 	let mut node_limit = match start.h
 	{
-		0...20 => 1000,
-		21...40 => 3000,
+		0..=20 => 1000,
+		21..=40 => 3000,
 		_ => 5000
 	};
 
@@ -25,44 +29,59 @@ pub fn solve(start: Map, solver: Solver) -> Result<(), String>
 	let mut increase_limit = 2000;
 	let mut nextgen_nodes = 1;
 
-	let mut info = Info::new(start.h);
+	let mut iter = 1;
+	let mut min_h = start.h;
+	solver.notify(Event::Started { h: start.h });
 	let mut open_max = 0;
 	let mut closed_max = 0;
-	let mut open_set: BinaryHeap<Node> = BinaryHeap::new();
+	let mut open_set = OpenList::new(solver.queue, solver.tie);
 	let mut limit = start.h;
 	let max_iter = 1000;
+	let mut best = start.clone();
 	open_set.push(start.clone());
 
-	let mut end_node = loop
+	let end_node = loop
 	{
-		let mut list: BinaryHeap<Node> = BinaryHeap::new();
-		let mut closed_set: HashSet<Map> = HashSet::new();
+		let mut list = OpenList::new(solver.queue, solver.tie);
+		let mut closed_set = ZobristSet::default();
 
-		if info.iter > max_iter
+		if iter > max_iter
 		{
-			return Err(format!("Search exceeded the iteration limit ({}) without finding a solution", max_iter));
+			return Err(Error::Limit(format!("search exceeded the iteration limit ({}) without finding a solution", max_iter)));
 		}
+		solver.notify(Event::Iteration { number: iter, bound: limit });
 		for _ in 0..nextgen_nodes
 		{
-			let node = open_set.pop().unwrap();
-			list.append(&mut expand_node(node, info.iter, limit, &mut closed_set, &solver));
-			let lowest = list.peek().unwrap();
-			if lowest.h == 0 { break }
+			let node = match open_set.pop()
+			{
+				Some(node) => node,
+				None => break
+			};
+			list.append(&mut expand_node(node, iter, limit, &mut closed_set, &mut best, &solver));
+			if solver.cancelled() { break }
+			if list.peek().is_some_and(|lowest| solver.is_goal(lowest)) { break }
 		}
+		if solver.cancelled() { return Err(interrupted(best, limit, open_max, closed_max, &solver)) }
 
-		let lowest = list.peek().unwrap();
+		let lowest = match list.peek()
+		{
+			Some(node) => node,
+			None => return Err(Error::Exhausted("no state reachable from the start is the goal".to_owned()))
+		};
 
 		if open_max < list.len() { open_max = list.len() }
 		if closed_max < closed_set.len() { closed_max = closed_set.len() }
 		let mut lowest_h = lowest.h;
+		let found = solver.is_goal(lowest);
 
+		if lowest.f > limit { solver.notify(Event::Bound { bound: lowest.f }) }
 		limit = lowest.f;
 		open_set = list;
 
 		nextgen_nodes = match open_set.len() > node_limit
 		{
 			false => open_set.len(),
-			true => match lowest_h < info.min_h
+			true => match lowest_h < min_h
 			{
 				true =>
 				{
@@ -84,43 +103,50 @@ pub fn solve(start: Map, solver: Solver) -> Result<(), String>
 					lowest_h = start.h;
 					open_max = 0;
 					closed_max = 0;
-					info = Info::new(start.h);
+					iter = 1;
+					solver.notify(Event::Started { h: start.h });
 					println!("Dead end. Reseting search and increasing limit nodes by {}", increase_limit.to_string().green());
 					increase_limit += increase_limit / 2;
 					1
 				}
 			}
 		};
-		info.update_ila(lowest_h, nextgen_nodes, open_max, closed_max);
+		solver.notify(Event::Best { h: lowest_h });
+		solver.notify(Event::Memory(vec![("nextgen nodes", nextgen_nodes), ("open states", open_max), ("closed states", closed_max),
+			("total states", open_max + closed_max)]));
+		min_h = lowest_h;
+		iter += 1;
 
-		if lowest_h == 0 { break open_set.pop().unwrap() }
+		if lowest_h == 0 && found { break open_set.pop().unwrap() }
 	};
 
-	info.bar.unwrap().finish();
-
 	let mut solution = Solution::new(open_max, closed_max);
-	let mut pos = end_node.pos;
-	let mut map;
-	let mut state = State { map: end_node.map, movement: Move::No };
-	while let Some(movement) = end_node.move_list.pop()
-	{
-		let opposite_move = movement.opposite();
-		state.movement = movement;
-		map = state.map.clone();
-		solution.path.push(state);
-		map = opposite_move.do_move(map, &pos, solver.size);
-		pos = pos.update(&opposite_move);
-		state = State { map: map, movement: Move::No };
-	}
+	solution.path = path_from_moves(end_node.map, &end_node.move_list, solver.size);
 	solution.moves = solution.path.len() - 1;
+	solution.cost = solver.path_cost(&solution.path);
+	solution.tie = Some(solver.tie);
+	solver.notify(Event::Solution { moves: solution.moves });
+	optimizer::apply(&mut solution, &solver);
 	solution.display(solver.size, solver.flag.verbosity, solver.time);
 	Ok(())
 }
 
-pub fn expand_node(node: Node, iter: usize, limit: usize, closed_set: &mut HashSet<Map>, solver: &Solver) -> BinaryHeap<Node>
+// Progress of the search when cancelled, from the best node expanded so far
+fn interrupted(best: Node, limit: usize, open_max: usize, closed_max: usize, solver: &Solver) -> Error
+{
+	Error::Interrupted(Box::new(Interrupted
+	{
+		path: path_from_moves(best.map, &best.move_list, solver.size),
+		best_h: best.h,
+		bound: limit,
+		stats: vec![("Maximum number of pending states (open set)", open_max), ("Maximum number of selected states (closed set)", closed_max)]
+	}))
+}
+
+pub fn expand_node(node: Node, iter: usize, limit: usize, closed_set: &mut ZobristSet, best: &mut Node, solver: &Solver) -> OpenList
 {
-	let mut open_set: BinaryHeap<Node> = BinaryHeap::new();
-	let mut node_list: BinaryHeap<Node> = BinaryHeap::new();
+	let mut open_set = OpenList::new(solver.queue, solver.tie);
+	let mut node_list = OpenList::new(solver.queue, solver.tie);
 
 	open_set.push(node);
 	loop
@@ -128,6 +154,7 @@ pub fn expand_node(node: Node, iter: usize, limit: usize, closed_set: &mut HashS
 		let current = open_set.pop();
 		if current.is_none() { break }
 		let mut current = current.unwrap();
+		if solver.cancelled() { break }
 		if current.f > limit
 		{
 			if current.depth < iter { continue }
@@ -136,30 +163,32 @@ pub fn expand_node(node: Node, iter: usize, limit: usize, closed_set: &mut HashS
 		}
 
 		// If the solution is found
-		if current.h == 0
+		if solver.is_goal(&current)
 		{
 			current.f = 0;
 			node_list.push(current);
 			break
 		}
 
+		if current.h < best.h { *best = current.clone() }
+
 		// Get the list of possible moves
-		let moves: Vec<Node> = current.generate_moves(solver.size);
+		let moves: Vec<Node> = current.generate_moves(solver.size, &solver.rules);
 
 		// Get the costs of child nodes and push them in the open set
 		for mut node in moves
 		{
-			if closed_set.contains(&node.map) { continue }
+			if closed_set.contains(&Key::new(&node, solver.confirm)) { continue }
 			node = solver.update_cost(node);
 			node.move_list = current.move_list.clone();
-			node.move_list.push(node.movement.clone());
+			node.move_list.push((node.movement.clone(), node.pos.clone()));
 			node.depth = iter;
 
 			if limit < node.f { node_list.push(node) }
 			else { open_set.push(node) }
 		}
 		
-		closed_set.insert(current.map);
+		closed_set.insert(Key::new(&current, solver.confirm));
 	}
 	node_list
 }
\ No newline at end of file
@@ -0,0 +1,272 @@
+use rand::{thread_rng, Rng};
+use rand::seq::SliceRandom;
+use colored::*;
+use std::time::Instant;
+
+use crate::{Map, Move, Flag, Container};
+use crate::node::Node;
+use crate::solver::Solver;
+use crate::generator::Generator;
+use crate::table::Table;
+use crate::astar_iterative_deepening;
+use crate::error::Error;
+
+pub const HEURISTICS: [&str; 4] = ["misplaced", "manhattan", "axes", "conflict"];
+// Length of each random walk from the goal used to sample big boards
+const WALK_LENGTH: usize = 60;
+// Sampled states are only compared with their true distance when it is cheap to find
+const ORACLE_DEPTH: usize = 16;
+
+// A state breaking a property, 'depth' being its distance (or an upper bound of it) to the goal
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Failure
+{
+	pub map: Map,
+	pub depth: usize,
+	pub movement: Move,
+	pub expected: usize,
+	pub found: usize
+}
+
+#[derive(Debug, Default)]
+pub struct Property
+{
+	pub checked: usize,
+	pub failures: usize,
+	// Failure closest to the goal
+	pub smallest: Option<Failure>
+}
+
+impl Property
+{
+	fn record(&mut self, failure: Option<Failure>)
+	{
+		self.checked += 1;
+		if let Some(failure) = failure
+		{
+			self.failures += 1;
+			if self.smallest.as_ref().is_none_or(|smallest| failure.depth < smallest.depth)
+			{
+				self.smallest = Some(failure);
+			}
+		}
+	}
+}
+
+#[derive(Debug)]
+pub struct Check
+{
+	pub heuristic: String,
+	// h after a move computed incrementally is equal to the full computation
+	pub incremental: Property,
+	// h never exceeds the true distance to the goal
+	pub admissible: Property,
+	// h changes by at most 1 with each move
+	pub consistent: Property
+}
+
+fn full_cost(map: Map, solver: &Solver) -> Node
+{
+	let mut node = Node::new(map);
+	node.find_position(solver.size);
+	(solver.first_cost)(node, &solver.end, solver.size)
+}
+
+// Check a heuristic on every state given with its depth, 'oracle' returning the true distance when known
+pub fn check(heuristic: &str, goal: &Map, size: usize, states: &[(Map, usize)], oracle: &dyn Fn(&Map, usize) -> Option<usize>) -> Check
+{
+	let solver = Solver::new(goal.clone(), size, heuristic, Flag::default(), Instant::now());
+	let mut check = Check
+	{
+		heuristic: heuristic.to_owned(),
+		incremental: Property::default(),
+		admissible: Property::default(),
+		consistent: Property::default()
+	};
+
+	for (map, depth) in states
+	{
+		let node = full_cost(map.clone(), &solver);
+		if let Some(distance) = oracle(map, *depth)
+		{
+			check.admissible.record(match node.h > distance
+			{
+				true => Some(Failure { map: map.clone(), depth: *depth, movement: Move::No, expected: distance, found: node.h }),
+				false => None
+			});
+		}
+		for child in node.generate_moves(size, &solver.rules)
+		{
+			let movement = child.movement.clone();
+			let incremental = (solver.update_cost)(child, &solver.end, size);
+			let full = full_cost(incremental.map.clone(), &solver);
+			let failure = |expected, found| Failure { map: map.clone(), depth: *depth, movement: movement.clone(), expected, found };
+			check.incremental.record(match incremental.h != full.h
+			{
+				true => Some(failure(full.h, incremental.h)),
+				false => None
+			});
+			check.consistent.record(match node.h.abs_diff(full.h) > 1
+			{
+				true => Some(failure(node.h, full.h)),
+				false => None
+			});
+		}
+	}
+	check
+}
+
+// Every 3x3 state that can reach the goal, closest first
+pub fn all_states(table: &Table) -> Vec<(Map, usize)>
+{
+	let mut states: Vec<(Map, usize)> = table
+		.states(0, usize::MAX)
+		.into_iter()
+		.map(|map| { let depth = table.distance(&map).unwrap(); (map, depth) })
+		.collect();
+	states.sort_by_key(|(_, depth)| *depth);
+	states
+}
+
+// States met along random walks from the goal, with the number of moves made to reach them
+pub fn sample_states(goal: &Map, size: usize, count: usize, rng: &mut impl Rng) -> Vec<(Map, usize)>
+{
+	let mut states = vec![];
+	while states.len() < count
+	{
+		let mut node = Node::new(goal.clone());
+		node.find_position(size);
+		for depth in 1..=WALK_LENGTH.min(count - states.len())
+		{
+			// Never undo the previous move
+			let previous = node.movement.opposite();
+			let moves: Vec<Move> = node.pos.possible_moves(size).into_iter().filter(|m| *m != Move::No && *m != previous).collect();
+			let movement = moves.choose(rng).unwrap().clone();
+			node.map = movement.do_move(node.map, &node.pos, size);
+			node.pos = node.pos.update(&movement, size);
+			node.movement = movement;
+			states.push((node.map.clone(), depth));
+		}
+	}
+	states
+}
+
+fn display_property(name: &str, property: &Property, size: usize)
+{
+	let smallest = match &property.smallest
+	{
+		None if property.checked == 0 => return println!("  {}: {}", name, "not checked".yellow()),
+		None => return println!("  {}: {} ({} checked)", name, "ok".green(), property.checked),
+		Some(smallest) => smallest
+	};
+	println!("  {}: {} out of {} checked, smallest counterexample {} moves from the goal:",
+		name, property.failures.to_string().red(), property.checked, smallest.depth);
+	for line in Container(smallest.map.clone(), size).to_string().lines()
+	{
+		println!("    {}", line);
+	}
+	match (name, &smallest.movement)
+	{
+		("admissible", _) => println!("    h = {} but the goal is {} moves away", smallest.found, smallest.expected),
+		("incremental", movement) => println!("    after {}: incremental h = {} but full h = {}", movement, smallest.found, smallest.expected),
+		(_, movement) => println!("    h = {} but after {} h = {}", smallest.expected, movement, smallest.found)
+	};
+}
+
+// Check every heuristic, on all the 3x3 states or on random states of bigger boards
+pub fn run(size: usize, goal: &str, samples: usize) -> Result<(), Error>
+{
+	let goal = Generator::generate_goal(goal, size);
+	let (states, table) = match size
+	{
+		3 =>
+		{
+			let table = Table::new(&goal);
+			(all_states(&table), Some(table))
+		},
+		_ => (sample_states(&goal, size, samples, &mut thread_rng()), None)
+	};
+	let exact = Solver::new(goal.clone(), size, "manhattan", Flag::default(), Instant::now());
+	let oracle = |map: &Map, depth: usize| match &table
+	{
+		Some(table) => table.distance(map),
+		None if depth <= ORACLE_DEPTH => astar_iterative_deepening::optimal_length(map.clone(), &exact),
+		None => None
+	};
+
+	println!("Checking {} states of size {}", states.len().to_string().cyan(), size);
+	for heuristic in HEURISTICS.iter()
+	{
+		let check = check(heuristic, &goal, size, &states, &oracle);
+		println!("{}", check.heuristic.bold());
+		display_property("incremental", &check.incremental, size);
+		display_property("admissible", &check.admissible, size);
+		display_property("consistent", &check.consistent, size);
+	}
+	Ok(())
+}
+
+#[cfg(test)]
+mod tests
+{
+	use rand::SeedableRng;
+	use rand::rngs::StdRng;
+	use crate::generator::Generator;
+	use crate::table::Table;
+	use super::{check, all_states, sample_states};
+
+	#[test]
+	fn every_3x3_state()
+	{
+		let goal = Generator::snail(3);
+		let table = Table::new(&goal);
+		let states: Vec<_> = all_states(&table).into_iter().take_while(|(_, depth)| *depth <= 12).collect();
+		assert!(states.windows(2).all(|pair| pair[0].1 <= pair[1].1));
+		let oracle = |map: &Vec<usize>, _| table.distance(map);
+
+		for heuristic in ["misplaced", "manhattan", "axes"].iter()
+		{
+			let result = check(heuristic, &goal, 3, &states, &oracle);
+			assert_eq!(result.admissible.checked, states.len());
+			for property in [&result.incremental, &result.admissible, &result.consistent].iter()
+			{
+				assert_eq!(property.failures, 0);
+			}
+		}
+
+		// Linear conflicts can raise h by 3 in a single move, first 12 moves away from the goal
+		let result = check("conflict", &goal, 3, &states, &oracle);
+		assert_eq!((result.incremental.failures, result.admissible.failures), (0, 0));
+		let smallest = result.consistent.smallest.unwrap();
+		assert_eq!(smallest.depth, 12);
+		assert_eq!(table.distance(&smallest.map), Some(12));
+		assert!(smallest.found > smallest.expected + 1);
+	}
+
+	#[test]
+	fn overestimating_oracle()
+	{
+		let goal = Generator::snail(3);
+		let table = Table::new(&goal);
+		let states: Vec<_> = all_states(&table).into_iter().take_while(|(_, depth)| *depth <= 4).collect();
+		// Pretend every state is one move closer than it is, which only holds for the goal
+		let result = check("manhattan", &goal, 3, &states, &|map, _| table.distance(map).map(|d| d.saturating_sub(1)));
+		let smallest = result.admissible.smallest.unwrap();
+		assert_eq!(smallest.depth, 1);
+		assert_eq!(smallest.found, 1);
+		assert_eq!(smallest.expected, 0);
+	}
+
+	#[test]
+	fn random_walks()
+	{
+		let goal = Generator::snail(3);
+		let table = Table::new(&goal);
+		let states = sample_states(&goal, 3, 500, &mut StdRng::seed_from_u64(42));
+		assert_eq!(states.len(), 500);
+		for (map, depth) in states
+		{
+			assert!(table.distance(&map).unwrap() <= depth);
+		}
+	}
+}
@@ -0,0 +1,257 @@
+use std::fmt;
+use std::fs;
+
+use crate::Map;
+use crate::solver::Solver;
+use crate::error::{Error, Location};
+
+// Seconds between two checkpoints when no interval is given
+pub const DEFAULT_INTERVAL: u64 = 60;
+
+// Where an IDA* search was, written to a file so that it can be resumed after a restart
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Checkpoint
+{
+	pub size: usize,
+	pub start: Map,
+	pub goal: Map,
+	pub heuristic: String,
+	pub torus: bool,
+	pub slides: bool,
+	pub uniform: bool,
+	pub weights: Option<Vec<usize>>,
+	pub bound: usize,
+	pub iteration: usize,
+	// Index of the child followed at each depth of the traversal, the last one being searched again
+	pub position: Vec<usize>,
+	// Lowest f above the bound met by the iteration so far
+	pub next_bound: usize,
+	// States expanded by the iteration so far, then by the iterations before it
+	pub expanded: usize,
+	pub max_expanded: usize,
+	pub total_expanded: usize
+}
+
+impl Checkpoint
+{
+	// Search of the puzzle by the solver that hasn't begun yet
+	pub fn new(start: &Map, bound: usize, solver: &Solver) -> Self
+	{
+		Self
+		{
+			size: solver.size,
+			start: start.clone(),
+			goal: solver.goal.clone(),
+			heuristic: solver.name.clone(),
+			torus: solver.rules.torus,
+			slides: solver.rules.slides,
+			uniform: solver.flag.uniform,
+			weights: solver.rules.weights.clone(),
+			bound,
+			iteration: 1,
+			position: vec![],
+			next_bound: usize::MAX,
+			expanded: 0,
+			max_expanded: 0,
+			total_expanded: 0
+		}
+	}
+
+	// A search can only be resumed with the puzzle, goal, heuristic and flags it was started with
+	pub fn check(&self, search: &Checkpoint) -> Result<(), Error>
+	{
+		let other = if self.size != search.size || self.start != search.start { Some("puzzle") }
+			else if self.goal != search.goal { Some("goal") }
+			else if self.heuristic != search.heuristic { Some("heuristic") }
+			else if (self.torus, self.slides, self.uniform, &self.weights) != (search.torus, search.slides, search.uniform, &search.weights) { Some("flags") }
+			else { None };
+		match other
+		{
+			Some(other) => Err(Error::Argument(format!("the checkpoint doesn't match the {} of this search", other))),
+			None => Ok(())
+		}
+	}
+
+	// Written next to the file then renamed over it, so that a restart never leaves half a checkpoint
+	pub fn create_file(&self, filepath: &str) -> Result<(), Error>
+	{
+		let temporary = format!("{}.tmp", filepath);
+		if let Err(e) = fs::write(&temporary, self.to_string())
+		{
+			return Err(Error::Io(format!("unable to write '{}': {}", temporary, e)))
+		}
+		if let Err(e) = fs::rename(&temporary, filepath)
+		{
+			return Err(Error::Io(format!("unable to write '{}': {}", filepath, e)))
+		}
+		Ok(())
+	}
+
+	pub fn from_file(filepath: &str) -> Result<Self, Error>
+	{
+		match fs::read_to_string(filepath)
+		{
+			Ok(text) => Self::parse(&text),
+			Err(e) => Err(Error::Io(format!("unable to read '{}': {}", filepath, e)))
+		}
+	}
+
+	// Once the search is solved there is nothing left to resume
+	pub fn remove_file(filepath: &str) -> Result<(), Error>
+	{
+		match fs::remove_file(filepath)
+		{
+			Ok(()) => Ok(()),
+			Err(e) => Err(Error::Io(format!("unable to remove '{}': {}", filepath, e)))
+		}
+	}
+
+	// One 'key value...' line per field, '#' starting a comment
+	pub fn parse(text: &str) -> Result<Self, Error>
+	{
+		let mut checkpoint = Self
+		{
+			size: 0, start: vec![], goal: vec![], heuristic: String::new(), torus: false, slides: false, uniform: false,
+			weights: None, bound: 0, iteration: 0, position: vec![], next_bound: 0, expanded: 0, max_expanded: 0, total_expanded: 0
+		};
+		let mut found = vec![];
+		for (i, line) in text.lines().enumerate()
+		{
+			let at = Location { line: i + 1, column: 1 };
+			let line = line.split('#').next().unwrap().trim();
+			if line.is_empty() { continue }
+			let mut words = line.split_whitespace();
+			let key = words.next().unwrap();
+			let values: Vec<&str> = words.collect();
+			let number = |text: &str| text.parse::<usize>().map_err(|_| Error::Syntax { message: format!("'{}' is not a number", text), at });
+			let numbers = || values.iter().map(|v| number(v)).collect::<Result<Vec<usize>, Error>>();
+			let single = || match values.as_slice()
+			{
+				[value] => Ok(*value),
+				_ => Err(Error::Syntax { message: format!("'{}' takes a single value", key), at })
+			};
+			let flag = || match single()?
+			{
+				"true" => Ok(true),
+				"false" => Ok(false),
+				value => Err(Error::Syntax { message: format!("'{}' is not true or false", value), at })
+			};
+			match key
+			{
+				"size" => checkpoint.size = number(single()?)?,
+				"start" => checkpoint.start = numbers()?,
+				"goal" => checkpoint.goal = numbers()?,
+				"heuristic" => checkpoint.heuristic = single()?.to_owned(),
+				"torus" => checkpoint.torus = flag()?,
+				"slides" => checkpoint.slides = flag()?,
+				"uniform" => checkpoint.uniform = flag()?,
+				"weights" if values == ["none"] => checkpoint.weights = None,
+				"weights" => checkpoint.weights = Some(numbers()?),
+				"bound" => checkpoint.bound = number(single()?)?,
+				"iteration" => checkpoint.iteration = number(single()?)?,
+				"position" => checkpoint.position = numbers()?,
+				"next_bound" => checkpoint.next_bound = number(single()?)?,
+				"expanded" => checkpoint.expanded = number(single()?)?,
+				"max_expanded" => checkpoint.max_expanded = number(single()?)?,
+				"total_expanded" => checkpoint.total_expanded = number(single()?)?,
+				_ => return Err(Error::Syntax { message: format!("unknown field '{}'", key), at })
+			};
+			found.push(key);
+		}
+		let at = Location { line: text.lines().count().max(1), column: 1 };
+		for key in ["size", "start", "goal", "heuristic", "bound", "iteration", "next_bound"]
+		{
+			if !found.contains(&key) { return Err(Error::Syntax { message: format!("missing field '{}'", key), at }) }
+		}
+		if checkpoint.start.len() != checkpoint.size * checkpoint.size || checkpoint.goal.len() != checkpoint.start.len()
+		{
+			return Err(Error::Syntax { message: "the puzzle and the goal don't have the size of the checkpoint".to_owned(), at })
+		}
+		Ok(checkpoint)
+	}
+}
+
+impl fmt::Display for Checkpoint
+{
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result
+	{
+		let list = |values: &[usize]| values.iter().map(|v| v.to_string()).collect::<Vec<String>>().join(" ");
+		writeln!(f, "# N-Puzzle IDA* checkpoint")?;
+		writeln!(f, "size {}", self.size)?;
+		writeln!(f, "start {}", list(&self.start))?;
+		writeln!(f, "goal {}", list(&self.goal))?;
+		writeln!(f, "heuristic {}", self.heuristic)?;
+		writeln!(f, "torus {}", self.torus)?;
+		writeln!(f, "slides {}", self.slides)?;
+		writeln!(f, "uniform {}", self.uniform)?;
+		match &self.weights
+		{
+			Some(weights) => writeln!(f, "weights {}", list(weights))?,
+			None => writeln!(f, "weights none")?
+		};
+		writeln!(f, "bound {}", self.bound)?;
+		writeln!(f, "iteration {}", self.iteration)?;
+		writeln!(f, "position {}", list(&self.position))?;
+		writeln!(f, "next_bound {}", self.next_bound)?;
+		writeln!(f, "expanded {}", self.expanded)?;
+		writeln!(f, "max_expanded {}", self.max_expanded)?;
+		writeln!(f, "total_expanded {}", self.total_expanded)
+	}
+}
+
+#[cfg(test)]
+mod tests
+{
+	use std::time::Instant;
+	use crate::Flag;
+	use crate::solver::Solver;
+	use crate::generator::Generator;
+	use crate::error::{Error, Location};
+	use super::Checkpoint;
+
+	#[test]
+	fn parse()
+	{
+		let start = vec![1, 2, 3, 8, 0, 4, 7, 6, 5];
+		let mut solver = Solver::new(Generator::snail(3), 3, "manhattan", Flag::default(), Instant::now());
+		solver.set_weights((0..9).collect()).unwrap();
+		let mut checkpoint = Checkpoint::new(&start, 12, &solver);
+		checkpoint.position = vec![2, 0, 1];
+		checkpoint.next_bound = 14;
+		checkpoint.total_expanded = 300;
+		assert_eq!(Checkpoint::parse(&checkpoint.to_string()), Ok(checkpoint.clone()));
+		checkpoint.position = vec![];
+		assert_eq!(Checkpoint::parse(&checkpoint.to_string()), Ok(checkpoint.clone()));
+
+		let text = checkpoint.to_string().replace("bound 12", "bound twelve");
+		assert_eq!(Checkpoint::parse(&text), Err(Error::Syntax { message: "'twelve' is not a number".to_owned(), at: Location { line: 10, column: 1 } }));
+		let text = checkpoint.to_string().replace("goal", "# goal");
+		assert_eq!(Checkpoint::parse(&text), Err(Error::Syntax { message: "missing field 'goal'".to_owned(), at: Location { line: 16, column: 1 } }));
+	}
+
+	#[test]
+	fn check()
+	{
+		let start = vec![1, 2, 3, 8, 0, 4, 7, 6, 5];
+		let solver = Solver::new(Generator::snail(3), 3, "manhattan", Flag::default(), Instant::now());
+		let checkpoint = Checkpoint::new(&start, 12, &solver);
+		let mut other = checkpoint.clone();
+		other.bound = 20;
+		other.position = vec![1, 1];
+		assert_eq!(checkpoint.check(&other), Ok(()));
+
+		let another = |other: &Checkpoint| checkpoint.check(other).unwrap_err().to_string();
+		let mut other = checkpoint.clone();
+		other.start.swap(0, 1);
+		assert_eq!(another(&other), "the checkpoint doesn't match the puzzle of this search");
+		let mut other = checkpoint.clone();
+		other.goal = Generator::classic(3);
+		assert_eq!(another(&other), "the checkpoint doesn't match the goal of this search");
+		let mut other = checkpoint.clone();
+		other.heuristic = "conflict".to_owned();
+		assert_eq!(another(&other), "the checkpoint doesn't match the heuristic of this search");
+		let mut other = checkpoint.clone();
+		other.uniform = true;
+		assert_eq!(another(&other), "the checkpoint doesn't match the flags of this search");
+	}
+}
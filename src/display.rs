@@ -3,15 +3,19 @@ use colored::*;
 use std::time::Instant;
 
 use crate::node::Node;
-use crate::{Container, Map, Move};
+use crate::observer::{Event, Observer};
+use crate::open_list::Tie;
+use crate::{Container, Map, Move, Position};
 
+// Progress bar showing how close to the goal the search got
 pub struct Info
 {
     pub bar: Option<ProgressBar>,
     pub max_h: usize,
     pub min_h: usize,
-    pub count: f32,
-    pub iter: usize
+    pub iter: Option<usize>,
+    pub bound: Option<usize>,
+    pub memory: Vec<(&'static str, usize)>
 }
 
 impl Info
@@ -21,38 +25,20 @@ impl Info
         Self
         {
             bar: None,
-            max_h: max_h,
+            max_h,
             min_h: max_h,
-            count: 0.0,
-            iter: 1
+            iter: None,
+            bound: None,
+            memory: vec![]
         }
     }
 
-    pub fn update(&mut self, current_h: usize, open_size: usize, closed_size: usize)
+    fn finish(&mut self)
     {
-        if self.bar.is_none()
-        {
-            let bar = ProgressBar::new(self.max_h as u64);
-            bar.set_style(ProgressStyle::default_bar()
-                .template(&format!("{{pos:}} of {:} | {{msg:}}", self.max_h)));
-            self.bar = Some(bar);
-        }
-
-        if let Some(ref bar) = self.bar
-        {
-            bar.set_position(self.count as u64);
-            self.count += (self.min_h - current_h) as f32;
-            let percent = self.count / (self.max_h as f32) * 100.0;
-            bar.set_message(&format!("{} | open states: {} | closed states: {} | total states: {}",
-                &format!("{:.2}%", percent).magenta(),
-                open_size.to_string().green(),
-                closed_size.to_string().red(),
-                (open_size + closed_size).to_string().cyan()));
-            self.min_h = current_h;
-        }
+        if let Some(bar) = self.bar.take() { bar.finish() }
     }
 
-    pub fn update_ia(&mut self, current_h: usize, open_size: usize, closed_size: usize)
+    fn draw(&mut self)
     {
         if self.bar.is_none()
         {
@@ -64,77 +50,49 @@ impl Info
 
         if let Some(ref bar) = self.bar
         {
-            bar.set_position(self.count as u64);
-            self.count += (self.min_h - current_h) as f32;
-            let percent = self.count / (self.max_h as f32) * 100.0;
-            bar.set_message(&format!("{} | iterations: {} | open states: {} | closed states: {} | total states: {}",
-                &format!("{:.2}%", percent).magenta(),
-                self.iter.to_string().yellow(),
-                open_size.to_string().green(),
-                closed_size.to_string().red(),
-                (open_size + closed_size).to_string().cyan()));
-            self.min_h = current_h;
-            self.iter += 1;
-        }
-    }
-
-    pub fn update_ila(&mut self, current_h: usize, nextgen_nodes: usize, open_size: usize, closed_size: usize)
-    {
-        if self.bar.is_none()
-        {
-            let bar = ProgressBar::new(self.max_h as u64);
-            bar.set_style(ProgressStyle::default_bar()
-                .template(&format!("{{pos:}} of {:} | {{msg:}}", self.max_h)));
-            self.bar = Some(bar);
-        }
-        if let Some(ref bar) = self.bar
-        {
-            bar.set_position(self.count as u64);
-            self.count += (self.min_h - current_h) as f32;
-            let percent = self.count / (self.max_h as f32) * 100.0;
-            bar.set_message(&format!("{} | iterations: {} | nextgen nodes: {} | open states: {} | closed states: {} | total states: {}",
-                &format!("{:.2}%", percent).magenta(),
-                self.iter.to_string().yellow(),
-                nextgen_nodes.to_string().blue(),
-                open_size.to_string().green(),
-                closed_size.to_string().red(),
-                (open_size + closed_size).to_string().cyan()));
-            self.min_h = current_h;
-            self.iter += 1;
+            let count = self.max_h - self.min_h;
+            bar.set_position(count as u64);
+            let percent = match self.max_h
+            {
+                0 => 100.0,
+                max_h => count as f32 / max_h as f32 * 100.0
+            };
+            let mut message = format!("{:.2}%", percent).magenta().to_string();
+            if let Some(iter) = self.iter
+            {
+                message.push_str(&format!(" | iterations: {}", iter.to_string().yellow()));
+            }
+            if let Some(bound) = self.bound
+            {
+                message.push_str(&format!(" | bound: {}", bound.to_string().bright_magenta()));
+            }
+            let colors = [Color::Green, Color::Red, Color::Cyan, Color::Blue];
+            for ((name, value), color) in self.memory.iter().zip(colors.iter().cycle())
+            {
+                message.push_str(&format!(" | {}: {}", name, value.to_string().color(*color)));
+            }
+            bar.set_message(&message);
         }
     }
+}
 
-    pub fn update_ida(&mut self, current_h: usize, max_expanded: usize, total_expanded: usize)
+impl Observer for Info
+{
+    fn notify(&mut self, event: Event)
     {
-        if self.bar.is_none()
+        match event
         {
-            let bar = ProgressBar::new(self.max_h as u64);
-            bar.set_style(ProgressStyle::default_bar()
-                .template(&format!("{{pos:}} of {:} | {{msg:}}", self.max_h)));
-            self.bar = Some(bar);
-        }
-
-        if let Some(ref bar) = self.bar
-        {
-            bar.set_position(self.count as u64);
-            let percent = match current_h < self.min_h
-            {
-                true =>
-                {
-                    self.count += (self.min_h - current_h) as f32;
-                    self.min_h = current_h;
-                    self.count / (self.max_h as f32) * 100.0
-                }
-                false => self.count / (self.max_h as f32) * 100.0
-            };
-
-            bar.set_message(&format!("{} | iterations: {} | max states: {} | total states: {}",
-                &format!("{:.2}%", percent).magenta(),
-                self.iter.to_string().yellow(),
-                max_expanded.to_string().green(),
-                total_expanded.to_string().red()));
-            self.iter += 1;
-        }
+            Event::Started { h } => { self.finish(); *self = Info::new(h) },
+            Event::Iteration { number, bound } => { self.iter = Some(number); self.bound = Some(bound) },
+            Event::Best { h } if h < self.min_h => self.min_h = h,
+            Event::Best { .. } => return,
+            Event::Bound { bound } => self.bound = Some(bound),
+            Event::Memory(memory) => self.memory = memory,
+            // Only completes a bar already shown, algorithms without progress don't get one
+            Event::Solution { .. } if self.bar.is_some() => { self.min_h = 0; self.draw(); self.finish(); return }
+            Event::Solution { .. } => return
+        };
+        self.draw();
     }
 }
 
@@ -172,16 +130,65 @@ impl Debug
     }
 }
 
+#[derive(Debug, Clone, PartialEq, Eq)]
 pub struct State
 {
 	pub map: Map,
 	pub movement: Move
 }
 
+impl State
+{
+	// Name of the move leading to this state, followed by the number of tiles it slid when there
+	// are several (multi-tile metric) and by the blank that moved when the previous state has
+	// several, numbered from 1 in reading order
+	pub fn move_name(&self, previous: Option<&State>) -> String
+	{
+		let previous = match previous
+		{
+			Some(previous) => previous,
+			None => return self.movement.to_string()
+		};
+		let mut name = self.movement.to_string();
+		// Every slid tile and the blank change cell
+		let changed = self.map.iter().zip(&previous.map).filter(|(a, b)| a != b).count();
+		if changed > 2
+		{
+			name.push_str(&format!(" {}", changed - 1));
+		}
+		if previous.map.iter().filter(|&&x| x == 0).count() > 1
+		{
+			if let Some(blank) = previous.map.iter().enumerate().filter(|(_, &x)| x == 0).position(|(i, _)| self.map[i] != 0)
+			{
+				name.push_str(&format!(", blank {}", blank + 1));
+			}
+		}
+		name
+	}
+}
+
+// Print every state from the start, the path being in the order of Solution
+fn display_path(path: &[State], size: usize)
+{
+	let mut previous = None;
+	for state in path.iter().rev()
+	{
+		println!("[{}]", state.move_name(previous));
+		println!("{}", Container(state.map.clone(), size));
+		previous = Some(state);
+	}
+}
+
 pub struct Solution
 {
 	pub path: Vec<State>,
 	pub moves: usize,
+	// Number of moves before the optimizer shortened the path
+	pub before: Option<usize>,
+	// Sum of the weights of the moved tiles, None when every move costs 1
+	pub cost: Option<usize>,
+	// Tie-breaking policy of the open list, None for the searches without one
+	pub tie: Option<Tie>,
 	pub selected: usize,
 	pub pending: usize,
 	pub total: usize
@@ -195,6 +202,9 @@ impl Solution
 		{
 			path: vec![],
 			moves: 0,
+			before: None,
+			cost: None,
+			tie: None,
 			pending: open_size,
 			selected: closed_size,
 			total: open_size + closed_size
@@ -203,34 +213,135 @@ impl Solution
 
     pub fn display(&mut self, size: usize, verbosity: bool, time: Instant)
     {
-        if verbosity == true
+        if verbosity
         {
-            while let Some(state) = self.path.pop()
-            {
-                println!("[{}]", state.movement);
-                println!("{}", Container(state.map, size));
-            }
+            display_path(&self.path, size);
             println!("Number of pending states (open set): {}", self.pending.to_string().green());
             println!("Number of selected states (closed set): {}", self.selected.to_string().red());
             println!("Number of states ever represented in memory: {}", self.total.to_string().cyan());
         }
+        if let Some(tie) = self.tie
+        {
+            println!("Tie-breaking: {}", tie.to_string().magenta());
+        }
+        if let Some(before) = self.before
+        {
+            println!("Number of moves before optimization: {}", before.to_string().yellow());
+        }
         println!("Number of moves: {}", self.moves.to_string().yellow());
+        if let Some(cost) = self.cost
+        {
+            println!("Total cost: {}", cost.to_string().yellow());
+        }
         println!("Execution time: {}", &format!("{:?}", time.elapsed()).bright_blue().bold());
     }
 
     pub fn display_ida(&mut self, size: usize, verbosity: bool, time: Instant)
     {
-        if verbosity == true
+        if verbosity
         {
-            while let Some(state) = self.path.pop()
-            {
-                println!("[{}]", state.movement);
-                println!("{}", Container(state.map, size));
-            }
+            display_path(&self.path, size);
             println!("Maximum number of states expanded: {}", self.pending.to_string().green());
             println!("Total number of states ever expanded: {}", self.selected.to_string().red());
         }
+        if let Some(before) = self.before
+        {
+            println!("Number of moves before optimization: {}", before.to_string().yellow());
+        }
         println!("Number of moves: {}", self.moves.to_string().yellow());
+        if let Some(cost) = self.cost
+        {
+            println!("Total cost: {}", cost.to_string().yellow());
+        }
         println!("Execution time: {}", &format!("{:?}", time.elapsed()).bright_blue().bold());
     }
-}
\ No newline at end of file
+}
+// Every optimal solution of a puzzle, or only their number
+pub struct Solutions
+{
+	// First optimal solutions found, each in the order of Solution
+	pub paths: Vec<Vec<State>>,
+	pub count: u128,
+	pub moves: usize,
+	// Sum of the weights of the moved tiles, None when every move costs 1
+	pub cost: Option<usize>
+}
+
+impl Solutions
+{
+	pub fn new(moves: usize, cost: Option<usize>) -> Self
+	{
+		Self { paths: vec![], count: 0, moves, cost }
+	}
+
+	// Each listed solution as its boards, or as its moves on a single line without verbosity
+	pub fn display(&self, size: usize, verbosity: bool, time: Instant)
+	{
+		for (i, path) in self.paths.iter().enumerate()
+		{
+			println!("{}", format!("Solution {}", i + 1).bold());
+			if verbosity { display_path(path, size); continue }
+			let moves: Vec<String> = (0..path.len() - 1).rev().map(|j| path[j].move_name(path.get(j + 1))).collect();
+			println!("{}", moves.join(", "));
+		}
+		println!("Number of moves: {}", self.moves.to_string().yellow());
+		if let Some(cost) = self.cost
+		{
+			println!("Total cost: {}", cost.to_string().yellow());
+		}
+		println!("Number of optimal solutions: {}", self.count.to_string().green());
+		println!("Execution time: {}", &format!("{:?}", time.elapsed()).bright_blue().bold());
+	}
+}
+
+// Path in the order of Solution (last state first) from the last state and the moves made
+// since the start, each with the position of the blank that moved
+pub fn path_from_moves(map: Map, moves: &[(Move, Position)], size: usize) -> Vec<State>
+{
+	let mut path = vec![];
+	let mut map = map;
+	for (movement, pos) in moves.iter().rev()
+	{
+		path.push(State { map: map.clone(), movement: movement.clone() });
+		map = movement.opposite().do_move(map, pos, size);
+	}
+	path
+}
+
+// What a search had reached when it was cancelled
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Interrupted
+{
+	// Path from the start to the state with the lowest h, in the order of Solution
+	pub path: Vec<State>,
+	pub best_h: usize,
+	// Bound of IDA* or f limit of the other algorithms
+	pub bound: usize,
+	pub stats: Vec<(&'static str, usize)>
+}
+
+impl Interrupted
+{
+	pub fn display(&self, size: usize, verbosity: bool, time: Instant)
+	{
+		println!("{}", "Search interrupted".red().bold());
+		match (verbosity, self.path.first())
+		{
+			(true, _) => display_path(&self.path, size),
+			(false, Some(state)) =>
+			{
+				println!("[{}]", state.move_name(self.path.get(1)));
+				println!("{}", Container(state.map.clone(), size));
+			},
+			(false, None) => ()
+		};
+		for (name, value) in &self.stats
+		{
+			println!("{}: {}", name, value.to_string().cyan());
+		}
+		println!("Lowest heuristic reached: {}", self.best_h.to_string().green());
+		println!("Number of moves to reach it: {}", (self.path.len().max(1) - 1).to_string().yellow());
+		println!("Bound when interrupted: {}", self.bound.to_string().magenta());
+		println!("Execution time: {}", &format!("{:?}", time.elapsed()).bright_blue().bold());
+	}
+}
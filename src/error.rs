@@ -0,0 +1,96 @@
+use std::fmt;
+use std::io;
+
+use crate::display::Interrupted;
+
+// Position of a parse error in the puzzle, both starting at 1
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Location
+{
+	pub line: usize,
+	pub column: usize
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Error
+{
+	// Invalid command line argument or option combination
+	Argument(String),
+	// File or standard input that can't be read or written
+	Io(String),
+	// Input whose format can't be detected or is malformed
+	Syntax { message: String, at: Location },
+	Size { size: usize, at: Location },
+	Rows { expected: usize, found: usize, at: Location },
+	Columns { expected: usize, found: usize, at: Location },
+	Tile { tile: String, at: Location },
+	Duplicate { tile: usize, at: Location },
+	Unsolvable,
+	// Every reachable state was searched without finding the goal
+	Exhausted(String),
+	// The search gave up after reaching one of its limits
+	Limit(String),
+	// The search was cancelled before reaching the goal, with what it found so far
+	Interrupted(Box<Interrupted>),
+	// Inconsistent result of a solver, should never happen
+	Internal(String)
+}
+
+impl Error
+{
+	// Exit code of the program for each category of error
+	pub fn exit_code(&self) -> i32
+	{
+		match self
+		{
+			Error::Internal(_) => 1,
+			Error::Argument(_) => 2,
+			Error::Io(_) => 3,
+			Error::Syntax { .. } | Error::Size { .. } | Error::Rows { .. }
+				| Error::Columns { .. } | Error::Tile { .. } | Error::Duplicate { .. } => 4,
+			Error::Unsolvable => 5,
+			Error::Exhausted(_) => 6,
+			Error::Limit(_) => 7,
+			Error::Interrupted(_) => 130
+		}
+	}
+}
+
+impl fmt::Display for Location
+{
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result
+	{
+		write!(f, "line {}, column {}", self.line, self.column)
+	}
+}
+
+impl fmt::Display for Error
+{
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result
+	{
+		match self
+		{
+			Error::Argument(message) | Error::Io(message) | Error::Exhausted(message)
+				| Error::Limit(message) | Error::Internal(message) => write!(f, "{}", message),
+			Error::Syntax { message, at } => write!(f, "{}: {}", at, message),
+			Error::Size { size, at } if *size < 3 => write!(f, "{}: size {} is too small, must be at least 3", at, size),
+			Error::Size { size, at } => write!(f, "{}: size {} is too big, must be equal or below 1000", at, size),
+			Error::Rows { expected, found, at } => write!(f, "{}: invalid number of rows, expected {} but found {}", at, expected, found),
+			Error::Columns { expected, found, at } => write!(f, "{}: invalid number of columns, expected {} but found {}", at, expected, found),
+			Error::Tile { tile, at } => write!(f, "{}: invalid tile '{}'", at, tile),
+			Error::Duplicate { tile, at } => write!(f, "{}: duplicate tile of '{}'", at, tile),
+			Error::Unsolvable => write!(f, "unsolvable puzzle"),
+			Error::Interrupted(_) => write!(f, "search interrupted before reaching the goal")
+		}
+	}
+}
+
+impl std::error::Error for Error {}
+
+impl From<io::Error> for Error
+{
+	fn from(error: io::Error) -> Self
+	{
+		Error::Io(error.to_string())
+	}
+}
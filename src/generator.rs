@@ -1,21 +1,39 @@
 extern crate rand;
 
-use rand::thread_rng;
+use rand::{thread_rng, Rng, SeedableRng};
+use rand::rngs::StdRng;
 use rand::seq::SliceRandom;
-use crate::{Map, Move, Position, Container};
+use std::time::Instant;
+use crate::{Map, Move, Position, Container, Flag};
+use crate::solver::{self, Solver};
+use crate::astar_iterative_deepening;
+use crate::table::Table;
+use crate::error::Error;
+
+// Number of puzzles tried before giving up on a requested solution length
+const MAX_ATTEMPTS: usize = 1000;
 
 pub struct Generator
 {
 	pub size: usize,
 	pub iter: usize,
+	pub random: bool,
+	pub length: Option<(usize, usize)>,
+	pub seed: u64,
+	pub rng: StdRng,
 	pub target: String,
-	pub pos: Position
+	pub pos: Position,
+	// Generate a puzzle for a toroidal board, shuffled with moves across the edges
+	pub torus: bool
 }
 
 impl Generator
 {
-	pub fn new(size: usize, iter: Option<usize>, level: &str, goal: &str, dir_path: &str) -> Self
+	pub fn new(size: usize, iter: Option<usize>, level: &str, goal: &str, dir_path: &str, seed: Option<u64>) -> Self
 	{
+		// Without a given seed one is still drawn so that it can be saved with the puzzle
+		let seed = seed.unwrap_or_else(|| thread_rng().gen());
+
 		let iterations = match iter
 		{
 			Some(i) => i,
@@ -25,6 +43,7 @@ impl Generator
 				"normal" => 25 * size,
 				"hard" => 100 * size,
 				"epic" => 10000 * size,
+				"random" => 0,
 				_ => 1
 			}
 		};
@@ -34,10 +53,15 @@ impl Generator
 
 		Generator
 		{
-			size: size,
+			size,
 			iter: iterations,
-			target: target,
-			pos: Position { x: 0, y: 0 }
+			random: iter.is_none() && level == "random",
+			length: None,
+			seed,
+			rng: StdRng::seed_from_u64(seed),
+			target,
+			pos: Position { x: 0, y: 0 },
+			torus: false
 		}
 	}
 
@@ -45,54 +69,124 @@ impl Generator
 	{
 		for _ in 0..self.iter
 		{
-			let moves: Vec<Move> = pos
-				.possible_moves(self.size)
+			let moves = match self.torus
+			{
+				true => pos.wrapping_moves(self.size),
+				false => pos.possible_moves(self.size)
+			};
+			let moves: Vec<Move> = moves
 				.into_iter()
 				.filter(|m| *m != Move::No)
 				.collect();
 
-			let mut rng = thread_rng();
-			let movement = moves.choose(&mut rng).unwrap();
+			let movement = moves.choose(&mut self.rng).unwrap();
 			map = movement.do_move(map, &pos, self.size);
-			pos = pos.update(movement);
+			pos = pos.update(movement, self.size);
 		}
 		self.pos = pos;
 		map
 	}
 
+	// Draw a uniformly random permutation then swap its first two tiles if its parity
+	// doesn't match the requested solvability. The swap pairs every solvable permutation
+	// with exactly one unsolvable one, so the result stays uniform among the wanted states.
+	pub fn random_map(&mut self, goal: &Map, solvable: bool) -> Map
+	{
+		let mut map = goal.clone();
+		map.shuffle(&mut self.rng);
+		if solver::is_solvable(&map, goal, self.size, self.torus) != solvable
+		{
+			let tiles: Vec<usize> = (0..map.len()).filter(|&i| map[i] != 0).take(2).collect();
+			map.swap(tiles[0], tiles[1]);
+		}
+		let index = map.iter().position(|&x| x == 0).unwrap();
+		self.pos = Position { x: index % self.size, y: index / self.size };
+		map
+	}
+
+	// Shuffle the goal until the optimal solution length is within [min, max].
+	// On a flat board or an even torus the length of a walk and of any solution have the same parity, so the
+	// walk starts at max moves and changes by 2 when the puzzle found is too easy or too hard. Crossing an edge
+	// of an odd torus flips that parity, so the walk changes by 1 there.
+	// 3x3 puzzles are directly drawn from the states of the complete table instead.
+	pub fn length_map(&mut self, goal: &Map, min: usize, max: usize) -> Result<(Map, usize), Error>
+	{
+		if self.size == 3 && !self.torus
+		{
+			let table = Table::new(goal);
+			let map = match table.states(min, max).choose(&mut self.rng)
+			{
+				Some(map) => map.clone(),
+				None => return Err(Error::Argument(format!("no 3x3 puzzle has an optimal solution between {} and {} moves", min, max)))
+			};
+			let moves = table.distance(&map).unwrap();
+			return Ok((map, moves))
+		}
+		let mut solver = Solver::new(goal.clone(), self.size, "manhattan", Flag::default(), Instant::now());
+		if self.torus { solver.set_torus()? }
+		let index = goal.iter().position(|&x| x == 0).unwrap();
+		let pos = Position { x: index % self.size, y: index / self.size };
+		self.iter = max;
+		let step = if self.torus && self.size % 2 == 1 { 1 } else { 2 };
+		for _ in 0..MAX_ATTEMPTS
+		{
+			let map = self.shuffle_map(goal.clone(), pos.clone());
+			match astar_iterative_deepening::optimal_length(map.clone(), &solver)
+			{
+				Some(moves) if moves < min => self.iter += step,
+				Some(moves) if moves > max => self.iter = self.iter.saturating_sub(step),
+				Some(moves) => return Ok((map, moves)),
+				None => ()
+			}
+		}
+		Err(Error::Limit(format!("unable to generate a puzzle with an optimal solution between {} and {} moves", min, max)))
+	}
+
 	pub fn get_unsolvable(&self, mut map: Map) -> Map
 	{
 		// Swap the first two tiles if empty is not there
 		if self.pos.x > 1 || self.pos.y > 0
 		{
-			let tmp = map[0];
-			map[0] = map[1];
-			map[1] = tmp;
+			map.swap(0, 1);
 		}
 		else // Swap the last two tiles
 		{
 			let last_index = map.len() - 1;
-			let tmp = map[last_index];
-			map[last_index] = map[last_index - 1];
-			map[last_index - 1] = tmp;
+			map.swap(last_index, last_index - 1);
 		}
 		map
 	}
 
-	pub fn generate_map(&mut self, goal: &str, solvable: bool) -> Result<String, String>
+	pub fn generate_map(&mut self, goal: &str, solvable: bool) -> Result<String, Error>
 	{
 		let map = Generator::generate_goal(goal, self.size);
+		let mut comments = vec![format!("seed: {}", self.seed)];
+		if !solvable && self.torus && self.size % 2 == 1
+		{
+			return Err(Error::Argument("every puzzle is solvable on a torus of odd size".to_owned()))
+		}
+		if let Some((min, max)) = self.length
+		{
+			if !solvable { return Err(Error::Argument("an unsolvable puzzle has no solution length".to_owned())) }
+			let (map, moves) = self.length_map(&map, min, max)?;
+			comments.push(format!("optimal solution: {} moves", moves));
+			Container(map, self.size).create_file(&self.target, &comments, self.torus)?;
+			return Ok(self.target.clone())
+		}
+		if self.random
+		{
+			let map = self.random_map(&map, solvable);
+			Container(map, self.size).create_file(&self.target, &comments, self.torus)?;
+			return Ok(self.target.clone())
+		}
 		let index = map.iter().position(|&x| x == 0).unwrap();
 		let pos = Position { x: index % self.size, y: index / self.size };
 		let mut map = self.shuffle_map(map, pos);
-		if solvable == false
+		if !solvable
 		{
 			map = self.get_unsolvable(map);
 		}
-		if let Err(e) = Container(map, self.size).create_file(&self.target)
-		{
-			return Err(e);
-		}
+		Container(map, self.size).create_file(&self.target, &comments, self.torus)?;
 		Ok(self.target.clone())
 	}
 
@@ -102,21 +196,28 @@ impl Generator
 		{
 			"classic" => Generator::classic(size),
 			"reversed" => Generator::reversed(size),
-			"snail" | _ => Generator::snail(size),
+			_ => Generator::snail(size),
 		}
 	}
 
+	// Goal of a puzzle with several blanks, the highest tiles of the goal being left empty
+	pub fn with_blanks(goal: Map, blanks: usize) -> Map
+	{
+		let tiles = goal.len() - blanks;
+		goal.into_iter().map(|x| if x > tiles { 0 } else { x }).collect()
+	}
+
 	pub fn classic(size: usize) -> Map
 	{
 		let mut goal: Map = (1..size * size).collect();
 		goal.push(0);
-		return goal;
+		goal
 	}
 
 	// Generate puzzle in reversed style
 	pub fn reversed(size: usize) -> Map
 	{
-		return (0..size * size).rev().collect();
+		(0..size * size).rev().collect()
 	}
 
 	// Generate puzzle in snail style
@@ -150,7 +251,7 @@ impl Generator
 			// Change fill direction
 			inc = -inc;
 		}
-		return goal;
+		goal
 	}
 }
 
@@ -240,4 +341,83 @@ mod tests
         assert_eq!(got, expected);
     }
 
+	#[test]
+	fn random_parity()
+	{
+		use crate::solver::is_solvable;
+
+		for &size in &[3, 4, 5]
+		{
+			let goal = super::Generator::snail(size);
+			let mut generator = super::Generator::new(size, None, "random", "snail", ".", None);
+			for _ in 0..50
+			{
+				let map = generator.random_map(&goal, true);
+				assert!(is_solvable(&map, &goal, size, false));
+				let map = generator.random_map(&goal, false);
+				assert!(!is_solvable(&map, &goal, size, false));
+				let mut sorted = map.clone();
+				sorted.sort();
+				assert_eq!(sorted, (0..size * size).collect::<Vec<usize>>());
+			}
+		}
+	}
+
+	#[test]
+	fn same_seed()
+	{
+		use crate::Position;
+
+		let goal = super::Generator::snail(4);
+		let mut a = super::Generator::new(4, None, "random", "snail", ".", Some(42));
+		let mut b = super::Generator::new(4, None, "random", "snail", ".", Some(42));
+		for _ in 0..10 { assert_eq!(a.random_map(&goal, true), b.random_map(&goal, true)) }
+
+		let mut a = super::Generator::new(4, Some(500), "normal", "snail", ".", Some(7));
+		let mut b = super::Generator::new(4, Some(500), "normal", "snail", ".", Some(7));
+		let pos = Position { x: 1, y: 2 };
+		assert_eq!(a.shuffle_map(goal.clone(), pos.clone()), b.shuffle_map(goal.clone(), pos.clone()));
+		assert_eq!(a.pos, b.pos);
+
+		let mut c = super::Generator::new(4, Some(500), "normal", "snail", ".", Some(8));
+		assert_ne!(a.shuffle_map(goal.clone(), pos.clone()), c.shuffle_map(goal, pos));
+	}
+
+	#[test]
+	fn exact_length()
+	{
+		use std::time::Instant;
+		use crate::Flag;
+		use crate::solver::Solver;
+		use crate::astar_iterative_deepening::optimal_length;
+
+		let goal = super::Generator::classic(4);
+		let solver = Solver::new(goal.clone(), 4, "manhattan", Flag::default(), Instant::now());
+		let mut generator = super::Generator::new(4, None, "normal", "classic", ".", Some(1));
+		for &(min, max) in &[(12, 12), (7, 9), (15, 15)]
+		{
+			let (map, moves) = generator.length_map(&goal, min, max).unwrap();
+			assert!(min <= moves && moves <= max);
+			assert_eq!(optimal_length(map, &solver), Some(moves));
+		}
+
+		let goal = super::Generator::snail(3);
+		let mut generator = super::Generator::new(3, None, "normal", "snail", ".", Some(1));
+		let (map, moves) = generator.length_map(&goal, 30, 30).unwrap();
+		assert_eq!(moves, 30);
+		assert_eq!(crate::table::Table::new(&goal).distance(&map), Some(30));
+		assert!(generator.length_map(&goal, 40, 50).is_err());
+
+		// Lengths of both parities on an odd torus
+		let mut solver = Solver::new(goal.clone(), 3, "manhattan", Flag::default(), Instant::now());
+		solver.set_torus().unwrap();
+		generator.torus = true;
+		for length in 7..=10
+		{
+			let (map, moves) = generator.length_map(&goal, length, length).unwrap();
+			assert_eq!(moves, length);
+			assert_eq!(optimal_length(map, &solver), Some(length));
+		}
+	}
+
 }
\ No newline at end of file
@@ -1,12 +1,12 @@
-use crate::Map;
+use crate::{Map, WILDCARD};
 use crate::node::Node;
 use std::collections::HashSet;
 
 #[inline]
 fn distance(a: usize, b: usize, n: usize) -> usize
 {
-	let x = (((a / n) as isize) - ((b / n) as isize)).abs() as usize;
-	let y = (((a % n) as isize) - ((b % n) as isize)).abs() as usize;
+	let x = (((a / n) as isize) - ((b / n) as isize)).unsigned_abs();
+	let y = (((a % n) as isize) - ((b % n) as isize)).unsigned_abs();
 	x + y
 }
 
@@ -16,7 +16,8 @@ pub fn manhattan(mut node: Node, end: &Map, size: usize) -> Node
 	for index in 0..(size * size)
 	{
 		let elem = node.map[index];
-		if elem == 0 { continue }
+		// Blanks and the tiles left free by a partial goal cost nothing
+		if elem == 0 || end[elem] == WILDCARD { continue }
 		node.cost[elem] = distance(index, end[elem], size);
 	}
 	node.h = node.cost.iter().sum();
@@ -26,8 +27,9 @@ pub fn manhattan(mut node: Node, end: &Map, size: usize) -> Node
 #[inline]
 pub fn partial_manhattan(mut node: Node, end: &Map, size: usize) -> Node
 {
-	let index = node.pos.moved_element(&node.movement).as_index(size);
+	let index = node.pos.moved_element(&node.movement, size).as_index(size);
 	let elem = node.map[index];
+	if end[elem] == WILDCARD { return node }
 	let prev_cost = distance(node.pos.as_index(size), end[elem], size);
 	let cost = distance(index, end[elem], size);
 	node.h = (node.h as i32 + (cost as i32 - prev_cost as i32)) as usize;
@@ -36,6 +38,41 @@ pub fn partial_manhattan(mut node: Node, end: &Map, size: usize) -> Node
 }
 
 
+// Shortest distance on a torus, where each axis can be crossed through the edges
+#[inline]
+fn torus_distance(a: usize, b: usize, n: usize) -> usize
+{
+	let x = ((a / n) as isize - (b / n) as isize).unsigned_abs();
+	let y = ((a % n) as isize - (b % n) as isize).unsigned_abs();
+	x.min(n - x) + y.min(n - y)
+}
+
+#[inline]
+pub fn torus_manhattan(mut node: Node, end: &Map, size: usize) -> Node
+{
+	for index in 0..(size * size)
+	{
+		let elem = node.map[index];
+		if elem == 0 || end[elem] == WILDCARD { continue }
+		node.cost[elem] = torus_distance(index, end[elem], size);
+	}
+	node.h = node.cost.iter().sum();
+	node
+}
+
+#[inline]
+pub fn partial_torus_manhattan(mut node: Node, end: &Map, size: usize) -> Node
+{
+	let index = node.pos.moved_element(&node.movement, size).as_index(size);
+	let elem = node.map[index];
+	if end[elem] == WILDCARD { return node }
+	let cost = torus_distance(index, end[elem], size);
+	node.h = (node.h as i32 + (cost as i32 - node.cost[elem] as i32)) as usize;
+	node.cost[elem] = cost;
+	node
+}
+
+
 // Returns the number of misplaced tiles in the puzzle expect the empty tile
 #[inline]
 pub fn misplaced_tiles(mut node: Node, end: &Map, size: usize) -> Node
@@ -43,7 +80,7 @@ pub fn misplaced_tiles(mut node: Node, end: &Map, size: usize) -> Node
 	for index in 0..(size * size)
 	{
 		let elem = node.map[index];
-		if elem == 0 { continue }
+		if elem == 0 || end[elem] == WILDCARD { continue }
 		node.cost[elem] = if index != end[elem] { 1 } else { 0 };
 	}
 	node.h = node.cost.iter().sum();
@@ -53,8 +90,9 @@ pub fn misplaced_tiles(mut node: Node, end: &Map, size: usize) -> Node
 #[inline]
 pub fn partial_misplaced(mut node: Node, end: &Map, size: usize) -> Node
 {
-	let index = node.pos.moved_element(&node.movement).as_index(size);
+	let index = node.pos.moved_element(&node.movement, size).as_index(size);
 	let elem = node.map[index];
+	if end[elem] == WILDCARD { return node }
 	let cost = (index != end[elem]) as usize;
 	node.h = (node.h as i32 + (cost as i32 - node.cost[elem] as i32)) as usize;
 	node.cost[elem] = cost;
@@ -69,7 +107,7 @@ pub fn out_of_axes(mut node: Node, end: &Map, size: usize) -> Node
 	for index in 0..(size * size)
 	{
 		let elem = node.map[index];
-		if elem == 0 { continue }
+		if elem == 0 || end[elem] == WILDCARD { continue }
 		node.cost[elem] = (index / size != end[elem] / size) as usize
 						+ (index % size != end[elem] % size) as usize;
 	}
@@ -80,8 +118,9 @@ pub fn out_of_axes(mut node: Node, end: &Map, size: usize) -> Node
 #[inline]
 pub fn partial_out_of_axes(mut node: Node, end: &Map, size: usize) -> Node
 {
-	let index = node.pos.moved_element(&node.movement).as_index(size);
+	let index = node.pos.moved_element(&node.movement, size).as_index(size);
 	let elem = node.map[index];
+	if end[elem] == WILDCARD { return node }
 	let cost = (index / size != end[elem] / size) as usize
 			+ (index % size != end[elem] % size) as usize;
 	node.h = (node.h as i32 + (cost as i32 - node.cost[elem] as i32)) as usize;
@@ -90,6 +129,62 @@ pub fn partial_out_of_axes(mut node: Node, end: &Map, size: usize) -> Node
 }
 
 
+// With the multi-tile metric a slide moves up to size - 1 tiles by one cell along a single axis,
+// so each count is divided by size - 1 to stay admissible. Every tile may move, h is always
+// computed from scratch and the same functions are used for the updates.
+#[inline]
+fn slides(count: usize, size: usize) -> usize
+{
+	count.div_ceil(size - 1)
+}
+
+// Vertical and horizontal distances are reduced by different slides
+#[inline]
+pub fn slide_manhattan(mut node: Node, end: &Map, size: usize) -> Node
+{
+	let (mut rows, mut columns) = (0, 0);
+	for index in 0..(size * size)
+	{
+		let elem = node.map[index];
+		if elem == 0 || end[elem] == WILDCARD { continue }
+		let y = (index / size).abs_diff(end[elem] / size);
+		let x = (index % size).abs_diff(end[elem] % size);
+		node.cost[elem] = x + y;
+		rows += y;
+		columns += x;
+	}
+	node.h = slides(rows, size) + slides(columns, size);
+	node
+}
+
+#[inline]
+pub fn slide_misplaced(mut node: Node, end: &Map, size: usize) -> Node
+{
+	node = misplaced_tiles(node, end, size);
+	node.h = slides(node.h, size);
+	node
+}
+
+// Only vertical slides bring tiles back to their row, and horizontal ones to their column
+#[inline]
+pub fn slide_out_of_axes(mut node: Node, end: &Map, size: usize) -> Node
+{
+	let (mut rows, mut columns) = (0, 0);
+	for index in 0..(size * size)
+	{
+		let elem = node.map[index];
+		if elem == 0 || end[elem] == WILDCARD { continue }
+		let y = (index / size != end[elem] / size) as usize;
+		let x = (index % size != end[elem] % size) as usize;
+		node.cost[elem] = x + y;
+		rows += y;
+		columns += x;
+	}
+	node.h = slides(rows, size) + slides(columns, size);
+	node
+}
+
+
 struct Conflict
 {
 	id: usize,
@@ -104,15 +199,17 @@ impl Conflict
 	{
 		Self
 		{
-			id: id,
-			start: start,
-			end: end,
+			id,
+			start,
+			end,
 			direction: 0
 		}
 	}
 
 	fn get_direction(&mut self, size: usize) -> bool
 	{
+		// A tile left free by a partial goal has no line to follow
+		if self.end == WILDCARD { return false }
 		let same_row = self.start / size == self.end / size;
 		let same_col = self.start % size == self.end % size;
 		match (same_row, same_col)
@@ -217,8 +314,8 @@ impl Conflict
 			cursor = (cursor as i32 + self.direction) as usize;
 			if start[cursor] == 0 || start[cursor] == self.id { continue }
 			let mut other = Conflict::new(start[cursor], cursor, end[start[cursor]]);
-			if other.get_direction(size) == false { continue }
-			if self.conflict_with(&other, self.direction) == false { continue }
+			if !other.get_direction(size) { continue }
+			if !self.conflict_with(&other, self.direction) { continue }
 			list.push(self.conflict_pair(&other));
 		}
 		list
@@ -235,9 +332,7 @@ impl Conflict
 			{
 				let mut other = Conflict::new(start[cursor], cursor, end[start[cursor]]);
 				if other.get_direction(size)
-				{
-					if self.conflict_with(&other, 1) { list.push(self.conflict_pair(&other)) }
-				}
+					&& self.conflict_with(&other, 1) { list.push(self.conflict_pair(&other)) }
 			}
 			cursor += 1;
 		}
@@ -255,9 +350,7 @@ impl Conflict
 			{
 				let mut other = Conflict::new(start[cursor], cursor, end[start[cursor]]);
 				if other.get_direction(size)
-				{
-					if self.conflict_with(&other, size as i32) { list.push(self.conflict_pair(&other)) }
-				}
+					&& self.conflict_with(&other, size as i32) { list.push(self.conflict_pair(&other)) }
 			}
 			cursor += size;
 		}
@@ -275,12 +368,12 @@ pub fn linear_conflict(mut node: Node, end: &Map, size: usize) -> Node
 	{
 		let id = node.map[index];
 		let mut elem = Conflict::new(id, index, end[id]);
-		if elem.id == 0 || elem.get_direction(size) == false { continue }
+		if elem.id == 0 || !elem.get_direction(size) { continue }
 		list.extend(elem.get_conflicts(&node.map, end, size));
 	}
 	// Remove duplicate pairs
 	let set: HashSet<_> = list.drain(..).collect();
-	list.extend(set.into_iter());
+	list.extend(set);
 
 	// Add the additional costs
 	for pair in &list
@@ -294,7 +387,7 @@ pub fn linear_conflict(mut node: Node, end: &Map, size: usize) -> Node
 
 pub fn partial_conflict(mut node: Node, end: &Map, size: usize) -> Node
 {
-	let index = node.pos.moved_element(&node.movement).as_index(size);
+	let index = node.pos.moved_element(&node.movement, size).as_index(size);
 	let id = node.map[index];
 
 	// Get conflict list of moved elem before the move
@@ -319,18 +412,20 @@ pub fn partial_conflict(mut node: Node, end: &Map, size: usize) -> Node
 	// Remove cost of old conflicts
 	for pair in &prev_list
 	{
-		// print!("({},{})", pair.0, pair.1);
 		node.cost[pair.0] -= 1;
 		node.cost[pair.1] -= 1;
 	}
 
+	// Update the distance of the moved elem before adding its new conflicts
+	// so that partial_manhattan doesn't overwrite them
+	node = partial_manhattan(node, end, size);
+
 	// Add the new conflicts costs
 	for pair in &list
 	{
 		node.cost[pair.0] += 1;
 		node.cost[pair.1] += 1;
 	}
-	node = partial_manhattan(node, end, size);
 	node.h = (node.h as i32 + 2 * (list.len() as i32 - prev_list.len() as i32)) as usize;
 	node
 }
@@ -338,8 +433,14 @@ pub fn partial_conflict(mut node: Node, end: &Map, size: usize) -> Node
 #[cfg(test)]
 mod tests
 {
-    use crate::Map;
+	use std::time::Instant;
+    use crate::{Map, Container, Rules, Flag};
 	use crate::node::Node;
+	use crate::generator::Generator;
+	use crate::solver::Solver;
+	use crate::display::State;
+	use crate::{Move, Position};
+	use crate::{astar, astar_iterative_deepening, oracle};
 
 	#[test]
 	fn distance()
@@ -404,4 +505,85 @@ mod tests
         assert_eq!(super::linear_conflict(start, &end, 4).h, 46);
 	}
 
-}
\ No newline at end of file
+	// The conflicts updated move by move match the ones computed on the whole state
+	#[test]
+	fn partial_conflict()
+	{
+		let end = Container(Generator::snail(4), 4).swap_indexes();
+		let mut start = Node::new(vec![12, 5, 14, 4, 10, 2, 11, 6, 15, 0, 3, 7, 13, 1, 9, 8]);
+		start.find_position(4);
+		let mut layer = vec![super::linear_conflict(start, &end, 4)];
+		for _ in 0..6
+		{
+			layer = layer.iter().flat_map(|node| node.generate_moves(4, &Rules::default())).map(|node| super::partial_conflict(node, &end, 4)).collect();
+			for node in &layer
+			{
+				assert_eq!(node.h, super::linear_conflict(Node::new(node.map.clone()), &end, 4).h);
+			}
+		}
+	}
+
+	// Across the edges of a torus the distance never overestimates, and A* stays optimal
+	#[test]
+	fn torus_manhattan()
+	{
+		let goal = Generator::snail(3);
+		let distance = oracle::distances(&goal, 3, &Rules { torus: true, ..Rules::default() });
+		// Every arrangement of an odd torus is reachable
+		assert_eq!(distance.len(), 362880);
+		let mut solver = Solver::new(goal.clone(), 3, "manhattan", Flag::default(), Instant::now());
+		solver.set_torus().unwrap();
+		for (map, &d) in distance.iter()
+		{
+			let mut node = Node::new(map.clone());
+			node.find_position(3);
+			assert!(solver.get_cost(node).h <= d);
+		}
+		let mut generator = Generator::new(3, None, "random", "snail", ".", Some(7));
+		for _ in 0..20
+		{
+			let map = generator.random_map(&goal, false);
+			assert_eq!(astar::find_path(map.clone(), &solver).unwrap().moves, distance[&map]);
+		}
+	}
+
+	// With the multi-tile metric the slide heuristics never overestimate and the searches stay optimal
+	#[test]
+	fn slides()
+	{
+		let goal = Generator::snail(3);
+		let mut solver = Solver::new(goal.clone(), 3, "manhattan", Flag::default(), Instant::now());
+		solver.set_slides().unwrap();
+		assert!(Solver::new(goal.clone(), 3, "conflict", Flag::default(), Instant::now()).set_slides().is_err());
+		let distance = oracle::distances(&goal, 3, &solver.rules);
+		assert_eq!(distance.len(), 181440);
+
+		for heuristic in ["misplaced", "manhattan", "axes"].iter()
+		{
+			let mut solver = Solver::new(goal.clone(), 3, heuristic, Flag::default(), Instant::now());
+			solver.set_slides().unwrap();
+			for (map, &d) in distance.iter()
+			{
+				let mut node = Node::new(map.clone());
+				node.find_position(3);
+				assert!(solver.get_cost(node).h <= d);
+			}
+		}
+
+		let mut generator = Generator::new(3, None, "random", "snail", ".", Some(7));
+		for _ in 0..20
+		{
+			let map = generator.random_map(&goal, true);
+			let solution = astar::find_path(map.clone(), &solver).unwrap();
+			assert_eq!(solution.moves, distance[&map]);
+			assert_eq!(astar_iterative_deepening::optimal_length(map.clone(), &solver), Some(distance[&map]));
+			assert_eq!(solution.path.last().unwrap().map, map);
+		}
+
+		// Sliding the two tiles left of the blank is a single move named after them
+		let previous = State { map: vec![1, 2, 0, 3, 4, 5, 6, 7, 8], movement: Move::No };
+		let next = State { map: vec![0, 1, 2, 3, 4, 5, 6, 7, 8], movement: Move::Left(-2) };
+		assert_eq!(Move::Left(-2).do_move(previous.map.clone(), &Position { x: 2, y: 0 }, 3), next.map);
+		assert_eq!(next.move_name(Some(&previous)), "Left 2");
+	}
+}
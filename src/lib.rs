@@ -2,44 +2,70 @@ use std::fmt;
 use std::fs::File;
 use std::io::Write;
 
+use crate::error::Error;
+
+pub mod error;
 pub mod solver;
 pub mod node;
 pub mod parser;
 pub mod heuristic;
 pub mod generator;
 pub mod display;
+pub mod observer;
+pub mod table;
+pub mod tree;
+pub mod check;
+pub mod quality;
+pub mod reduction;
+pub mod optimizer;
+pub mod checkpoint;
+pub mod open_list;
+pub mod zobrist;
+#[cfg(test)]
+mod oracle;
 
 pub mod astar;
 pub mod astar_iterative;
 pub mod astar_iterative_limited;
 pub mod astar_iterative_deepening;
+pub mod astar_iterative_deepening_parallel;
+pub mod astar_hash_distributed;
 
 pub type Map = Vec<usize>;
 
+// Cell of a partial goal where any tile may end, written '*' in a goal file
+pub const WILDCARD: usize = usize::MAX;
+
 pub struct Container(pub Map, pub usize);
 
 impl Container
 {
+    // Index of each tile in the map, WILDCARD for the tiles a partial goal doesn't place
     pub fn swap_indexes(&self) -> Map
     {
         self.0
 			.iter()
 			.enumerate()
-			.fold(vec![0; self.0.len()], | mut acc, (i, x) | { acc[*x] = i; acc } )
+			.filter(|(_, x)| **x != WILDCARD)
+			.fold(vec![WILDCARD; self.0.len()], | mut acc, (i, x) | { acc[*x] = i; acc } )
     }
 
-	pub fn create_file(&self, filepath: &str) -> Result<(), String>
+	// Write the puzzle in the parser format with each comment on its own '#' line,
+	// the size being followed by 'torus' for a toroidal board
+	pub fn create_file(&self, filepath: &str, comments: &[String], torus: bool) -> Result<(), Error>
 	{
 		let mut file = match File::create(filepath)
 		{
 			Ok(f) => Ok(f),
-			Err(e) => Err(e.to_string())
+			Err(e) => Err(Error::Io(format!("unable to create '{}': {}", filepath, e)))
 		}?;
 
-		let data = format!("{}\n{}", self.1, self.to_string());
+		let header: String = comments.iter().map(|c| format!("# {}\n", c)).collect();
+		let board = if torus { " torus" } else { "" };
+		let data = format!("{}{}{}\n{}", header, self.1, board, self);
 		if let Err(e) = file.write_all(data.as_bytes())
 		{
-			return Err(e.to_string())
+			return Err(Error::Io(format!("unable to write '{}': {}", filepath, e)))
 		};
 		Ok(())
 	}
@@ -59,11 +85,36 @@ impl fmt::Display for Container
                 _ => to_display.push_str(&format!("\t{}", self.0[i]))
             }
         }
-		to_display.push_str("\n");
+		to_display.push('\n');
         write!(f, "{}", to_display)
     }
 }
 
+// How the tiles move: across the edges on a torus, several at once, and at which cost
+#[derive(Debug, Clone, Default)]
+pub struct Rules
+{
+	pub torus: bool,
+	// A whole run of tiles along a row or a column slides in a single move (multi-tile metric)
+	pub slides: bool,
+	// Cost of sliding each tile, indexed by its number, every move costing 1 without them
+	pub weights: Option<Vec<usize>>
+}
+
+impl Rules
+{
+	#[inline]
+	pub fn cost(&self, tile: usize) -> usize
+	{
+		match &self.weights
+		{
+			Some(weights) => weights[tile],
+			None => 1
+		}
+	}
+}
+
+#[derive(Default)]
 pub struct Flag
 {
 	pub verbosity: bool,
@@ -86,39 +137,17 @@ impl Position
 		self.y * size + self.x
 	}
 
-	pub fn update(&self, movement: &Move) -> Position
+	// Position reached with a move, whose offset may wrap around the board on a torus
+	pub fn update(&self, movement: &Move, size: usize) -> Position
 	{
-		match movement
-		{
-			Move::Left(x) | Move::Right(x) => Position
-			{
-				x: (self.x as i64 + x) as usize,
-				y: self.y
-			},
-			Move::Up(_) => Position
-			{
-				x: self.x,
-				y: (self.y as i64 - 1) as usize
-			},
-			Move::Down(_) => Position
-			{
-				x: self.x,
-				y: (self.y as i64 + 1) as usize
-			},
-			Move::No => Position {x: self.x, y: self.y }
-		}
+		let index = (self.as_index(size) as i64 + movement.get_offset()) as usize;
+		Position { x: index % size, y: index / size }
 	}
 
-	pub fn moved_element(&self, movement: &Move) -> Position
+	// Position of the tile that slid into the blank, the blank being at self after the move
+	pub fn moved_element(&self, movement: &Move, size: usize) -> Position
 	{
-		match movement
-		{
-			Move::Left(_) => Position { x: self.x + 1, y: self.y },
-			Move::Right(_) => Position { x: self.x - 1, y: self.y },
-			Move::Up(_) => Position { x: self.x, y: self.y + 1 },
-			Move::Down(_) => Position { x: self.x, y: self.y - 1 },
-			Move::No => Position { x: self.x, y: self.y }
-		}
+		self.update(&movement.opposite(), size)
 	}
 
 	#[inline]
@@ -132,6 +161,32 @@ impl Position
 			if self.y < size - 1 { Move::Down(size as i64) } else { Move::No }
 		]
 	}
+
+	// Moves on a torus, where leaving an edge brings the blank to the opposite one
+	#[inline]
+	pub fn wrapping_moves(&self, size: usize) -> Vec<Move>
+	{
+		let (last, row) = (size as i64 - 1, size as i64);
+		vec!
+		[
+			if self.x > 0 { Move::Left(-1) } else { Move::Left(last) },
+			if self.x < size - 1 { Move::Right(1) } else { Move::Right(-last) },
+			if self.y > 0 { Move::Up(-row) } else { Move::Up(last * row) },
+			if self.y < size - 1 { Move::Down(row) } else { Move::Down(-last * row) }
+		]
+	}
+
+	// Moves of the multi-tile metric, the blank jumping any distance along its row or its column
+	pub fn sliding_moves(&self, size: usize) -> Vec<Move>
+	{
+		let row = size as i64;
+		let mut moves = vec![];
+		moves.extend((1..=self.x as i64).map(|k| Move::Left(-k)));
+		moves.extend((1..(size - self.x) as i64).map(Move::Right));
+		moves.extend((1..=self.y as i64).map(|k| Move::Up(-k * row)));
+		moves.extend((1..(size - self.y) as i64).map(|k| Move::Down(k * row)));
+		moves
+	}
 }
 
 #[derive(Debug, Clone, Eq, PartialEq)]
@@ -146,16 +201,50 @@ pub enum Move
 
 impl Move
 {
+	// Slide the tiles between the blank and its new position, each of them moving by one cell
 	pub fn do_move(&self, mut map: Map, pos: &Position, size: usize) -> Map
 	{
-		let pos = pos.as_index(size);
-		let new_pos = (pos as i64 + self.get_offset()) as usize;
-		let tmp = map[new_pos];
-		map[new_pos] = map[pos];
-		map[pos] = tmp;
+		let pos = pos.as_index(size) as i64;
+		match self.length(size)
+		{
+			length if length > 1 =>
+			{
+				let step = self.step(size);
+				for i in 0..length as i64
+				{
+					map.swap((pos + i * step) as usize, (pos + (i + 1) * step) as usize);
+				}
+			},
+			_ => map.swap(pos as usize, (pos + self.get_offset()) as usize)
+		};
 		map
 	}
 
+	// Offset of the blank moving by a single cell in the direction of the move
+	pub fn step(&self, size: usize) -> i64
+	{
+		match self
+		{
+			Move::Left(_) => -1,
+			Move::Right(_) => 1,
+			Move::Up(_) => -(size as i64),
+			Move::Down(_) => size as i64,
+			Move::No => 0
+		}
+	}
+
+	// Number of tiles slid by the move, a move across the edges of a torus sliding a single one
+	pub fn length(&self, size: usize) -> usize
+	{
+		let (offset, step) = (self.get_offset(), self.step(size));
+		match self
+		{
+			Move::No => 0,
+			_ if offset.signum() != step.signum() => 1,
+			_ => (offset / step) as usize
+		}
+	}
+
 	pub fn get_offset(&self) -> i64
 	{
 		match self
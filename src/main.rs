@@ -1,75 +1,315 @@
 use std::process::exit;
 use colored::*;
 use clap::{App, load_yaml};
-use std::time::{Instant};
+use std::time::{Duration, Instant};
+use std::sync::atomic::Ordering;
 
-use npuzzle::{Flag, Container};
+use npuzzle::{Map, Flag, Container, WILDCARD};
+use npuzzle::error::Error;
 use npuzzle::solver::Solver;
 use npuzzle::generator::Generator;
 use npuzzle::parser;
-use npuzzle::{astar, astar_iterative, astar_iterative_limited, astar_iterative_deepening};
+use npuzzle::{astar, astar_iterative, astar_iterative_limited, astar_iterative_deepening, astar_hash_distributed, reduction, check, quality, optimizer, checkpoint};
+use npuzzle::checkpoint::Checkpoint;
+use npuzzle::open_list::{Queue, Tie};
 
 struct Args
 {
 	pub file: String,
 	pub goal: String,
+	// Goal read from a file, possibly with wildcards
+	pub goal_file: Option<String>,
 	pub g_size: String,
 	pub level: String,
 	pub iter: Option<String>,
+	pub seed: Option<String>,
+	pub length: Option<String>,
 	pub algo: String,
 	pub heuristic: String,
+	// The heuristic was chosen on the command line rather than left to its default
+	pub heuristic_given: bool,
+	pub threads: String,
+	pub queue: Queue,
+	pub tie: String,
+	pub tie_seed: Option<String>,
+	pub confirm_hashes: bool,
+	pub tree: Option<String>,
+	pub tree_nodes: String,
+	// Window of the path optimizer, in moves, when it is asked for
+	pub window: Option<String>,
+	// Number of optimal solutions listed, all of them being counted, when they are asked for
+	pub solutions: Option<String>,
+	// Files IDA* writes its progress to and resumes from
+	pub checkpoint: Option<String>,
+	pub checkpoint_interval: Option<String>,
+	pub resume: Option<String>,
+	pub check: Option<String>,
+	pub quality: Option<String>,
+	pub samples: String,
 	pub solvable: bool,
+	pub torus: bool,
+	pub weights: Option<String>,
+	// Every tile slid along a row or a column counts as a single move
+	pub slides: bool,
 	pub flag: Flag
 }
 
-// Display error message on standard error and exit program
-fn exit_program(message: &str)
+// Display error message on standard error and exit program with the code of its category
+fn exit_program(error: &Error)
 {
-	eprintln!("Error: {}", message.red());
-	exit(1);
+	eprintln!("Error: {}", error.to_string().red());
+	exit(error.exit_code());
 }
 
-fn parse_number(number: &str) -> Result<usize, String>
+fn parse_number(number: &str) -> Result<usize, Error>
 {
 	match number.parse()
 	{
 		Ok(n) => Ok(n),
-		Err(_) => Err(format!("'{}' must be a valid number", number))
+		Err(_) => Err(Error::Argument(format!("'{}' must be a valid number", number)))
 	}
 }
 
-fn run_program(args: Args, time: Instant) -> Result<(), String>
+// A goal read from a file must have the size of the puzzle and only place tiles the puzzle has
+fn check_goal(start: &Map, goal: &Container, size: usize) -> Result<(), Error>
 {
-	let file = if args.g_size == "None" { args.file } else
+	if goal.1 != size
+	{
+		return Err(Error::Argument(format!("the goal has a size of {} but the puzzle has a size of {}", goal.1, size)))
+	}
+	let last = start.len() - start.iter().filter(|&&x| x == 0).count();
+	if let Some(tile) = goal.0.iter().find(|&&x| x != WILDCARD && x > last)
+	{
+		return Err(Error::Argument(format!("tile {} of the goal is not in the puzzle", tile)))
+	}
+	if goal.0.iter().filter(|&&x| x == 0).count() > size * size - last
+	{
+		return Err(Error::Argument("the goal has more blanks than the puzzle".to_owned()))
+	}
+	Ok(())
+}
+
+fn run_program(args: Args, time: Instant) -> Result<(), Error>
+{
+	if let Some(size) = args.check
+	{
+		let size = parse_number(&size)?;
+		if !(3..=100).contains(&size)
+		{
+			return Err(Error::Argument("checked puzzle size must be between 3 and 100".to_owned()));
+		}
+		return check::run(size, &args.goal, parse_number(&args.samples)?)
+	}
+	if let Some(size) = args.quality
+	{
+		let size = parse_number(&size)?;
+		if !(3..=100).contains(&size)
+		{
+			return Err(Error::Argument("measured puzzle size must be between 3 and 100".to_owned()));
+		}
+		return quality::run(size, &args.goal, parse_number(&args.samples)?)
+	}
+	let file = if args.g_size == "None" { args.file }
+	else
 	{
 		let g_size = parse_number(&args.g_size)?;
-		if g_size < 3 || g_size > 1000
+		if !(3..=1000).contains(&g_size)
 		{
-			return Err("generated puzzle size must be between 3 and 1000".to_owned());
+			return Err(Error::Argument("generated puzzle size must be between 3 and 1000".to_owned()));
 		}
 		let iter = match args.iter
 		{
 			Some(i) => Some(parse_number(&i)?),
 			None => None
 		};
-		let mut generator = Generator::new(g_size, iter, &args.level, &args.goal, &args.file);
+		let seed = match args.seed
+		{
+			Some(s) => match s.parse()
+			{
+				Ok(n) => Some(n),
+				Err(_) => return Err(Error::Argument(format!("'{}' must be a valid seed", s)))
+			},
+			None => None
+		};
+		let mut generator = Generator::new(g_size, iter, &args.level, &args.goal, &args.file, seed);
+		generator.torus = args.torus;
+		if let Some(length) = args.length
+		{
+			// Either an exact number of moves or an inclusive range as 'min-max'
+			let range = match length.find('-')
+			{
+				Some(i) => (parse_number(&length[..i])?, parse_number(&length[i + 1..])?),
+				None => (parse_number(&length)?, parse_number(&length)?)
+			};
+			if range.0 > range.1 { return Err(Error::Argument(format!("'{}' is not a valid range of moves", length))) }
+			generator.length = Some(range);
+		}
 		generator.generate_map(&args.goal, args.solvable)?
 	};
 
 	// Get start map & size inside Container
-	let Container(start, size) = parser::get_map(&file)?;
-	let end = Generator::generate_goal(&args.goal, size);
-	if start == end { return Err("the puzzle is already solved...".to_owned()) }
-	let solver = Solver::new(end, size, &args.heuristic, args.flag, time);
+	let (Container(start, size), torus) = parser::get_map(&file)?;
+	let blanks = start.iter().filter(|&&x| x == 0).count();
+	let end = match &args.goal_file
+	{
+		Some(filename) =>
+		{
+			let goal = parser::get_goal(filename)?;
+			check_goal(&start, &goal, size)?;
+			goal.0
+		},
+		None => Generator::with_blanks(Generator::generate_goal(&args.goal, size), blanks)
+	};
+	let partial = end.contains(&WILDCARD);
+	let torus = torus || args.torus;
+	// The default linear conflicts don't apply to a torus, to weighted moves or to slides, manhattan distance is used instead
+	let heuristic = match (torus || args.weights.is_some() || args.slides) && !args.heuristic_given
+	{
+		true => "manhattan",
+		false => &args.heuristic
+	};
+	let mut solver = Solver::new(end, size, heuristic, args.flag, time);
+	if torus { solver.set_torus()? }
+	if solver.matches(&start) { return Err(Error::Argument("the puzzle is already solved...".to_owned())) }
 	solver.is_solvable(&start)?;
+	solver.threads = match parse_number(&args.threads)?
+	{
+		0 => return Err(Error::Argument("number of threads must be at least 1".to_owned())),
+		n => n
+	};
+	if solver.threads > 1 && args.algo != "IDA*" && args.algo != "HDA*"
+	{
+		return Err(Error::Argument("only IDA* and HDA* can search with several threads".to_owned()));
+	}
+
+	// Ctrl-C stops the search, which then reports the best state it reached
+	let cancel = solver.cancel.clone();
+	if ctrlc::set_handler(move || cancel.store(true, Ordering::Relaxed)).is_err()
+	{
+		return Err(Error::Internal("unable to handle the interrupt signal".to_owned()))
+	}
+	let (size, verbosity) = (solver.size, solver.flag.verbosity);
+	solver.queue = args.queue;
+	solver.confirm = args.confirm_hashes;
+	solver.tie = match (args.tie.as_ref(), args.tie_seed)
+	{
+		("random", Some(seed)) => match seed.parse()
+		{
+			Ok(n) => Tie::Random(n),
+			Err(_) => return Err(Error::Argument(format!("'{}' must be a valid seed", seed)))
+		},
+		("random", None) => Tie::Random(rand::random()),
+		(_, Some(_)) => return Err(Error::Argument("a tie-breaking seed only applies to the random policy".to_owned())),
+		("low-h", _) => Tie::LowH,
+		("high-g", _) => Tie::HighG,
+		("lifo", _) => Tie::Lifo,
+		("fifo", _) => Tie::Fifo,
+		_ => Tie::Cost
+	};
+	if solver.tie != Tie::Cost && ["IDA*", "reduction"].contains(&args.algo.as_ref())
+	{
+		return Err(Error::Argument("tie-breaking policies only apply to the open lists of A*, IA*, ILA* and HDA*".to_owned()))
+	}
 
-	match args.algo.as_ref()
+	if blanks > 1 && args.algo == "reduction"
+	{
+		return Err(Error::Argument("the reduction algorithm only solves puzzles with a single blank".to_owned()))
+	}
+	if partial && args.algo == "reduction"
+	{
+		return Err(Error::Argument("the reduction algorithm only solves complete goals".to_owned()))
+	}
+	if solver.rules.torus && args.algo == "reduction"
+	{
+		return Err(Error::Argument("the reduction algorithm only solves flat puzzles".to_owned()))
+	}
+	if let Some(model) = args.weights
+	{
+		if args.algo == "reduction"
+		{
+			return Err(Error::Argument("the reduction algorithm only solves puzzles whose moves all cost 1".to_owned()))
+		}
+		// Either each tile costs its number, or the costs are read from a file
+		let tiles = size * size - blanks;
+		let weights = match model.as_ref()
+		{
+			"number" => (0..=tiles).collect(),
+			filepath => parser::get_weights(filepath, tiles)?
+		};
+		solver.set_weights(weights)?;
+	}
+	if args.slides
+	{
+		if args.algo == "reduction"
+		{
+			return Err(Error::Argument("the reduction algorithm only solves puzzles with single-tile moves".to_owned()))
+		}
+		solver.set_slides()?;
+	}
+	if args.tree.is_some() && args.algo != "A*"
+	{
+		return Err(Error::Argument("the explored graph can only be written with A*".to_owned()))
+	}
+	if let Some(window) = args.window
+	{
+		if !["A*", "IA*", "ILA*"].contains(&args.algo.as_ref())
+		{
+			return Err(Error::Argument("only the paths found by A*, IA* and ILA* can be optimized".to_owned()))
+		}
+		solver.window = match parse_number(&window)?
+		{
+			0 | 1 => return Err(Error::Argument("the optimizer window must be at least 2 moves".to_owned())),
+			n => Some(n)
+		};
+	}
+	let list = match args.solutions
+	{
+		Some(_) if args.algo != "IDA*" => return Err(Error::Argument("optimal solutions can only be counted with IDA*".to_owned())),
+		Some(_) if solver.threads > 1 => return Err(Error::Argument("optimal solutions can only be counted by a single thread".to_owned())),
+		Some(list) if list == "all" => Some(usize::MAX),
+		Some(list) => Some(parse_number(&list)?),
+		None => None
+	};
+	// Checkpoints go to the file resumed from unless another one is given
+	let checkpoints = args.checkpoint.or(args.resume.clone());
+	if checkpoints.is_some() && (args.algo != "IDA*" || solver.threads > 1 || list.is_some())
+	{
+		return Err(Error::Argument("only single-threaded IDA* searches for one solution can be checkpointed".to_owned()))
+	}
+	let resume = match args.resume
 	{
-		"IDA*" => astar_iterative_deepening::solve(start, solver),
+		Some(filepath) => Some(Checkpoint::from_file(&filepath)?),
+		None => None
+	};
+	let interval = match args.checkpoint_interval
+	{
+		Some(_) if checkpoints.is_none() => return Err(Error::Argument("the checkpoint interval needs --checkpoint or --resume".to_owned())),
+		Some(seconds) => Duration::from_secs(parse_number(&seconds)? as u64),
+		None => Duration::from_secs(checkpoint::DEFAULT_INTERVAL)
+	};
+	let result = match args.algo.as_ref()
+	{
+		"IDA*" => match (list, checkpoints)
+		{
+			(Some(list), _) => astar_iterative_deepening::solve_all(start, solver, list),
+			(None, Some(filepath)) => astar_iterative_deepening::solve_resumable(start, solver, Some(filepath), interval, resume),
+			(None, None) => astar_iterative_deepening::solve(start, solver)
+		},
 		"IA*" => astar_iterative::solve(start, solver),
 		"ILA*" => astar_iterative_limited::solve(start, solver),
-		"A*" | _ => astar::solve(start, solver),
+		"HDA*" => astar_hash_distributed::solve(start, solver),
+		"reduction" => reduction::solve(start, solver),
+		_ => match args.tree
+		{
+			Some(filepath) => astar::solve_tree(start, solver, &filepath, parse_number(&args.tree_nodes)?),
+			None => astar::solve(start, solver)
+		},
+	};
+	if let Err(Error::Interrupted(ref progress)) = result
+	{
+		progress.display(size, verbosity, time);
 	}
+	result
 }
 
 fn main()
@@ -82,18 +322,48 @@ fn main()
 
 	let args = Args
 	{
-		file: matches.value_of("file").unwrap().to_owned(),
+		file: matches.value_of("file").unwrap_or_default().to_owned(),
 		goal: matches.value_of("end_mode").unwrap().to_owned(),
+		goal_file: matches.value_of("goal").map(|g| g.to_owned()),
 		g_size: matches.value_of("generator").unwrap().to_owned(),
 		level: matches.value_of("level").unwrap().to_owned(),
-		iter: match matches.value_of("iterations")
-		{
-			Some(i) => Some(i.to_owned()),
-			None => None
-		},
+		iter: matches.value_of("iterations").map(|i| i.to_owned()),
+		seed: matches.value_of("seed").map(|s| s.to_owned()),
+		length: matches.value_of("length").map(|l| l.to_owned()),
 		solvable: matches.value_of("solvability").unwrap() == "solvable",
+		torus: matches.is_present("torus"),
+		weights: matches.value_of("weights").map(|w| w.to_owned()),
+		slides: matches.value_of("metric").unwrap() == "multi",
 		algo: matches.value_of("algorithm").unwrap().to_owned(),
 		heuristic: matches.value_of("heuristic_function").unwrap().to_owned(),
+		heuristic_given: matches.occurrences_of("heuristic_function") > 0,
+		threads: matches.value_of("threads").unwrap().to_owned(),
+		queue: match matches.value_of("queue").unwrap()
+		{
+			"buckets" => Queue::Buckets,
+			_ => Queue::Heap
+		},
+		tie: matches.value_of("tie").unwrap().to_owned(),
+		tie_seed: matches.value_of("tie_seed").map(|s| s.to_owned()),
+		confirm_hashes: matches.is_present("confirm_hashes"),
+		tree: matches.value_of("tree").map(|t| t.to_owned()),
+		tree_nodes: matches.value_of("tree_nodes").unwrap_or("1000").to_owned(),
+		window: match matches.is_present("optimize")
+		{
+			true => Some(matches.value_of("window").unwrap_or(&optimizer::DEFAULT_WINDOW.to_string()).to_owned()),
+			false => None
+		},
+		solutions: match (matches.is_present("count"), matches.value_of("solutions"))
+		{
+			(true, _) => Some("0".to_owned()),
+			(false, list) => list.map(|l| l.to_owned())
+		},
+		checkpoint: matches.value_of("checkpoint").map(|c| c.to_owned()),
+		checkpoint_interval: matches.value_of("checkpoint_interval").map(|i| i.to_owned()),
+		resume: matches.value_of("resume").map(|r| r.to_owned()),
+		check: matches.value_of("check").map(|c| c.to_owned()),
+		quality: matches.value_of("quality").map(|q| q.to_owned()),
+		samples: matches.value_of("samples").unwrap_or("10000").to_owned(),
 		flag: Flag
 		{
 			verbosity: matches.is_present("verbosity"),
@@ -102,8 +372,8 @@ fn main()
 			uniform: matches.is_present("uniform")
 		}
 	};
-	if let Err(ref message) = run_program(args, time)
+	if let Err(ref error) = run_program(args, time)
 	{
-		exit_program(message);
+		exit_program(error);
 	}
 }
@@ -1,5 +1,6 @@
 use std::cmp::Ordering;
-use crate::{Map, Move, Position};
+use crate::{Map, Move, Position, Rules};
+use crate::zobrist;
 
 #[derive(Debug, Clone, Eq, PartialEq)]
 pub struct Node
@@ -13,17 +14,27 @@ pub struct Node
 	pub g: usize,
 	pub f: usize,
 	pub t: usize,
-	pub move_list: Vec<Move>,
+	// Zobrist hash of the map, updated with the cells each move changes
+	pub hash: u64,
+	// Moves made since the start, each with the position of the blank after it
+	pub move_list: Vec<(Move, Position)>,
 }
 
 impl Node
 {
 	pub fn new(map: Map) -> Self
+	{
+		let hash = zobrist::hash(&map);
+		Self::with_hash(map, hash)
+	}
+
+	fn with_hash(map: Map, hash: u64) -> Self
 	{
 		Self
 		{
 			cost: vec![0; map.len()],
-			map: map,
+			hash,
+			map,
 			pos: Position { x: 0, y: 0 },
 			movement: Move::No,
 			move_list: vec![],
@@ -35,6 +46,7 @@ impl Node
 		}
 	}
 
+	// Position of the first blank in reading order
 	pub fn find_position(&mut self, size: usize)
 	{
 		let index = self.map.iter().position(|&x| x == 0).unwrap();
@@ -42,21 +54,48 @@ impl Node
 		self.pos.y = index / size;
 	}
 
-	pub fn generate_moves(&self, size: usize) -> Vec<Self>
+	// Every state reached by sliding a tile (or a run of tiles) into one of the blanks, following
+	// the rules of the board, with 'pos' set to the position of the blank that moved
+	pub fn generate_moves(&self, size: usize, rules: &Rules) -> Vec<Self>
 	{
-		let possible_moves = self.pos.possible_moves(size);
 		let mut moves: Vec<Node> = vec![];
-		for movement in &possible_moves
+		for index in (0..self.map.len()).filter(|&i| self.map[i] == 0)
 		{
-			if *movement == Move::No { continue }
-			let map = movement.do_move(self.map.clone(), &self.pos, size);
-			let mut node = Node::new(map);
-			node.cost = self.cost.clone();
-			node.pos = self.pos.update(&movement);
-			node.movement = movement.clone();
-			node.g = self.g + 1;
-			node.h = self.h;
-			moves.push(node);
+			let pos = Position { x: index % size, y: index / size };
+			let directions = match (rules.torus, rules.slides)
+			{
+				(true, _) => pos.wrapping_moves(size),
+				(false, true) => pos.sliding_moves(size),
+				(false, false) => pos.possible_moves(size)
+			};
+			for movement in &directions
+			{
+				if *movement == Move::No { continue }
+				// Swapping two blanks doesn't change the state, and a slide stops before another blank
+				let tile = self.map[(index as i64 + movement.get_offset()) as usize];
+				if tile == 0 { continue }
+				let step = movement.step(size);
+				if (1..movement.length(size) as i64).any(|i| self.map[(index as i64 + i * step) as usize] == 0) { continue }
+				// Two slides of the same blank along the same line are worth at most one
+				if rules.slides && index == self.pos.as_index(size) && step.abs() == self.movement.step(size).abs() { continue }
+				let map = movement.do_move(self.map.clone(), &pos, size);
+				// The blank and every slid tile change cell, a move across the edges of a torus only swapping two
+				let (last, stride) = match movement.length(size)
+				{
+					length if length > 1 => (length as i64, step),
+					_ => (1, movement.get_offset())
+				};
+				let hash = (0..=last).map(|i| (index as i64 + i * stride) as usize)
+					.fold(self.hash, |hash, i| hash ^ zobrist::key(i, self.map[i]) ^ zobrist::key(i, map[i]));
+				debug_assert_eq!(hash, zobrist::hash(&map), "incremental Zobrist hash differs from recomputation");
+				let mut node = Node::with_hash(map, hash);
+				node.cost = self.cost.clone();
+				node.pos = pos.update(movement, size);
+				node.movement = movement.clone();
+				node.g = self.g + rules.cost(tile);
+				node.h = self.h;
+				moves.push(node);
+			}
 		}
 		moves
 	}
@@ -66,7 +105,7 @@ impl Ord for Node
 {
     fn cmp(&self, other: &Node) -> Ordering
 	{
-        self.partial_cmp(other).unwrap()
+		other.f.cmp(&self.f).then(other.t.cmp(&self.t))
     }
 }
 
@@ -74,6 +113,55 @@ impl PartialOrd for Node
 {
     fn partial_cmp(&self, other: &Node) -> Option<Ordering>
 	{
-		Some(other.f.cmp(&self.f).then(other.t.cmp(&self.t)))
+		Some(self.cmp(other))
     }
-}
\ No newline at end of file
+}
+
+#[cfg(test)]
+mod tests
+{
+	use std::collections::HashSet;
+	use std::time::Instant;
+	use crate::{Flag, Rules};
+	use crate::solver::Solver;
+	use crate::generator::Generator;
+	use crate::oracle;
+	use crate::{astar, astar_iterative_deepening, astar_hash_distributed};
+	use super::Node;
+
+	// Every blank moves, each child sliding a single tile into one of them, and the searches stay optimal
+	#[test]
+	fn several_blanks()
+	{
+		let start = vec![5, 1, 0, 3, 0, 6, 2, 4, 9, 10, 7, 8, 12, 11, 0, 13];
+		let mut node = Node::new(start.clone());
+		node.find_position(4);
+		let children = node.generate_moves(4, &Rules::default());
+		// Three tiles next to each of the three blanks
+		assert_eq!(children.len(), 9);
+		assert_eq!(children.iter().map(|child| child.map.clone()).collect::<HashSet<_>>().len(), 9);
+		for child in &children
+		{
+			let changed: Vec<usize> = (0..16).filter(|&i| start[i] != child.map[i]).collect();
+			assert_eq!(changed.len(), 2);
+			assert!(changed.iter().any(|&i| start[i] == 0));
+			assert_eq!(child.map[child.pos.as_index(4)], 0);
+		}
+
+		let goal = Generator::with_blanks(Generator::snail(4), 3);
+		let mut solver = Solver::new(goal.clone(), 4, "manhattan", Flag::default(), Instant::now());
+		solver.threads = 3;
+		let solution = astar_hash_distributed::find_path(start.clone(), &solver).unwrap();
+		assert_eq!(solution.moves, astar::find_path(start.clone(), &solver).unwrap().moves);
+		assert_eq!(solution.path.last().unwrap().map, start);
+		assert_eq!(solution.path[0].map, goal);
+
+		let goal = Generator::with_blanks(Generator::snail(3), 2);
+		let start = vec![0, 1, 3, 7, 2, 4, 0, 6, 5];
+		let solver = Solver::new(goal.clone(), 3, "conflict", Flag::default(), Instant::now());
+		assert_eq!(oracle::distance(&start, 3, &Rules::default(), |map| *map == goal), Some(3));
+		assert_eq!(astar_hash_distributed::find_path(start.clone(), &solver).unwrap().moves, 3);
+		assert_eq!(astar::find_path(start.clone(), &solver).unwrap().moves, 3);
+		assert_eq!(astar_iterative_deepening::optimal_length(start, &solver), Some(3));
+	}
+}
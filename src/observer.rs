@@ -0,0 +1,97 @@
+// Progress of a search, sent by the algorithms to the observer of their solver
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Event
+{
+	// The search starts (or restarts) from a state with this heuristic
+	Started { h: usize },
+	// A new iteration of IA*, ILA* or IDA* begins with the given f limit or bound
+	Iteration { number: usize, bound: usize },
+	// A state closer to the goal than any before was reached
+	Best { h: usize },
+	// The f limit or bound of an iterative algorithm was raised
+	Bound { bound: usize },
+	// Number of states held or expanded by the search so far, with a short name for each
+	Memory(Vec<(&'static str, usize)>),
+	Solution { moves: usize }
+}
+
+// Receives the progress of a search, either to display it or to log it
+pub trait Observer
+{
+	fn notify(&mut self, event: Event);
+}
+
+// Observer ignoring every event
+pub struct Silent;
+
+impl Observer for Silent
+{
+	fn notify(&mut self, _event: Event) {}
+}
+
+#[cfg(test)]
+mod tests
+{
+	use std::sync::{Arc, Mutex};
+	use std::time::Instant;
+	use crate::Flag;
+	use crate::solver::Solver;
+	use crate::generator::Generator;
+	use crate::{astar, astar_iterative_deepening};
+	use super::{Event, Observer};
+
+	struct Recorder(Arc<Mutex<Vec<Event>>>);
+
+	impl Observer for Recorder
+	{
+		fn notify(&mut self, event: Event)
+		{
+			self.0.lock().unwrap().push(event);
+		}
+	}
+
+	fn recorded(solver: &mut Solver) -> Arc<Mutex<Vec<Event>>>
+	{
+		let events = Arc::new(Mutex::new(vec![]));
+		solver.observer = Mutex::new(Box::new(Recorder(events.clone())));
+		events
+	}
+
+	const START: [usize; 9] = [5, 4, 3, 1, 0, 2, 6, 7, 8];
+
+	#[test]
+	fn astar_events()
+	{
+		let mut solver = Solver::new(Generator::snail(3), 3, "manhattan", Flag::default(), Instant::now());
+		let events = recorded(&mut solver);
+		let solution = astar::find_path(START.to_vec(), &solver).unwrap();
+		let events = events.lock().unwrap();
+
+		assert!(matches!(events[0], Event::Started { .. }));
+		assert_eq!(events.last(), Some(&Event::Solution { moves: solution.moves }));
+		let best: Vec<usize> = events.iter().filter_map(|e| match e { Event::Best { h } => Some(*h), _ => None }).collect();
+		assert!(best.windows(2).all(|pair| pair[1] < pair[0]));
+	}
+
+	#[test]
+	fn iterative_deepening_events()
+	{
+		let mut solver = Solver::new(Generator::snail(3), 3, "manhattan", Flag::default(), Instant::now());
+		let events = recorded(&mut solver);
+		astar_iterative_deepening::solve(START.to_vec(), solver).unwrap();
+		let events = events.lock().unwrap();
+
+		let iterations: Vec<(usize, usize)> = events.iter()
+			.filter_map(|e| match e { Event::Iteration { number, bound } => Some((*number, *bound)), _ => None })
+			.collect();
+		let bounds: Vec<usize> = events.iter().filter_map(|e| match e { Event::Bound { bound } => Some(*bound), _ => None }).collect();
+		assert_eq!(iterations.len(), bounds.len() + 1);
+		for (i, &(number, bound)) in iterations.iter().enumerate().skip(1)
+		{
+			assert_eq!(number, i + 1);
+			assert_eq!(bound, bounds[i - 1]);
+			assert!(bound > iterations[i - 1].1);
+		}
+		assert!(matches!(events.last(), Some(Event::Solution { .. })));
+	}
+}
@@ -0,0 +1,478 @@
+use std::cmp::Ordering;
+use std::collections::{BinaryHeap, VecDeque};
+use std::fmt;
+use rand::{Rng, SeedableRng};
+use rand::rngs::StdRng;
+use crate::node::Node;
+
+// Priority queue holding the open states of A*, IA*, ILA* and HDA*
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Queue
+{
+	#[default]
+	Heap,
+	// Two-level bucket queue indexed by f then by the tie-breaking key, costs being small integers
+	Buckets
+}
+
+// Which of the open states of equal f is expanded first
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Tie
+{
+	// Lower t, which is h in A* and g in greedy searches
+	#[default]
+	Cost,
+	LowH,
+	HighG,
+	// Last pushed first, or first pushed first
+	Lifo,
+	Fifo,
+	// Order drawn for each pushed node by a generator seeded with the seed, the same on every run with that seed
+	Random(u64)
+}
+
+impl fmt::Display for Tie
+{
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result
+	{
+		match self
+		{
+			Tie::Cost => write!(f, "cost"),
+			Tie::LowH => write!(f, "low-h"),
+			Tie::HighG => write!(f, "high-g"),
+			Tie::Lifo => write!(f, "lifo"),
+			Tie::Fifo => write!(f, "fifo"),
+			Tie::Random(seed) => write!(f, "random (seed {})", seed)
+		}
+	}
+}
+
+// Open list popping the node with the lowest f, ties being broken by its policy
+pub struct OpenList
+{
+	tie: Tie,
+	// Number of nodes ever pushed, ordering them for LIFO and FIFO
+	pushes: u64,
+	// Generator of the random policy, drawing the order of each node in both queues
+	rng: Option<StdRng>,
+	entries: Entries
+}
+
+enum Entries
+{
+	Heap(BinaryHeap<Entry>),
+	Buckets(Buckets)
+}
+
+// Node of the heap with its keys after f, both lower first
+struct Entry
+{
+	rank: u64,
+	order: u64,
+	node: Node
+}
+
+impl Ord for Entry
+{
+	fn cmp(&self, other: &Entry) -> Ordering
+	{
+		other.node.f.cmp(&self.node.f).then(other.rank.cmp(&self.rank)).then(other.order.cmp(&self.order))
+	}
+}
+
+impl PartialOrd for Entry
+{
+	fn partial_cmp(&self, other: &Entry) -> Option<Ordering>
+	{
+		Some(self.cmp(other))
+	}
+}
+
+impl PartialEq for Entry
+{
+	fn eq(&self, other: &Entry) -> bool
+	{
+		self.cmp(other) == Ordering::Equal
+	}
+}
+
+impl Eq for Entry {}
+
+impl OpenList
+{
+	pub fn new(queue: Queue, tie: Tie) -> Self
+	{
+		let entries = match queue
+		{
+			Queue::Heap => Entries::Heap(BinaryHeap::new()),
+			Queue::Buckets => Entries::Buckets(Buckets::new(tie))
+		};
+		let rng = match tie
+		{
+			Tie::Random(seed) => Some(StdRng::seed_from_u64(seed)),
+			_ => None
+		};
+		Self { tie, pushes: 0, rng, entries }
+	}
+
+	pub fn push(&mut self, node: Node)
+	{
+		self.pushes += 1;
+		let drawn = self.rng.as_mut().map_or(0, |rng| rng.gen());
+		match &mut self.entries
+		{
+			Entries::Heap(heap) =>
+			{
+				let (rank, order) = match self.tie
+				{
+					Tie::Cost => (node.t as u64, 0),
+					Tie::LowH => (node.h as u64, 0),
+					Tie::HighG => (u64::MAX - node.g as u64, 0),
+					Tie::Lifo => (0, u64::MAX - self.pushes),
+					Tie::Fifo => (0, self.pushes),
+					Tie::Random(_) => (0, drawn)
+				};
+				heap.push(Entry { rank, order, node })
+			},
+			Entries::Buckets(buckets) => buckets.push(node, drawn)
+		}
+	}
+
+	pub fn pop(&mut self) -> Option<Node>
+	{
+		match &mut self.entries
+		{
+			Entries::Heap(heap) => heap.pop().map(|entry| entry.node),
+			Entries::Buckets(buckets) => buckets.pop()
+		}
+	}
+
+	pub fn peek(&self) -> Option<&Node>
+	{
+		match &self.entries
+		{
+			Entries::Heap(heap) => heap.peek().map(|entry| &entry.node),
+			Entries::Buckets(buckets) => buckets.peek()
+		}
+	}
+
+	pub fn len(&self) -> usize
+	{
+		match &self.entries
+		{
+			Entries::Heap(heap) => heap.len(),
+			Entries::Buckets(buckets) => buckets.len
+		}
+	}
+
+	pub fn is_empty(&self) -> bool
+	{
+		self.len() == 0
+	}
+
+	pub fn clear(&mut self)
+	{
+		match &mut self.entries
+		{
+			Entries::Heap(heap) => heap.clear(),
+			Entries::Buckets(buckets) => *buckets = Buckets::new(self.tie)
+		}
+	}
+
+	// Move every node of the other list into this one, pushed after the nodes it already holds
+	// and in the order they were pushed into the other list
+	pub fn append(&mut self, other: &mut OpenList)
+	{
+		let ordered = matches!(self.tie, Tie::Lifo | Tie::Fifo | Tie::Random(_));
+		if let (Entries::Heap(heap), Entries::Heap(other), false) = (&mut self.entries, &mut other.entries, ordered)
+		{
+			return heap.append(other)
+		}
+		let mut nodes = vec![];
+		while let Some(node) = other.pop() { nodes.push(node) }
+		// The last node pushed comes out first
+		if other.tie == Tie::Lifo { nodes.reverse() }
+		for node in nodes { self.push(node) }
+	}
+}
+
+// Nodes grouped by f then by the key of the tie-breaking policy, h, g or t, LIFO and FIFO keeping a single
+// group per f ordered as a stack or a queue. The random policy keeps a heap of the orders drawn per f
+// instead, popping nodes as the binary heap does at the price of a logarithmic push. Rows start at the lowest f holding
+// nodes, so that weighted costs only take room for the span of f in the list, and each row keeps its best
+// key holding nodes up to date so that peek is O(1).
+pub struct Buckets
+{
+	tie: Tie,
+	rows: VecDeque<Row>,
+	// f of the first row
+	base: usize,
+	len: usize
+}
+
+// Nodes of a single f, with their number and the best key holding nodes
+#[derive(Default)]
+struct Row
+{
+	groups: Vec<VecDeque<Node>>,
+	drawn: BinaryHeap<Entry>,
+	size: usize,
+	best: usize
+}
+
+impl Buckets
+{
+	pub fn new(tie: Tie) -> Self
+	{
+		Self { tie, rows: VecDeque::new(), base: 0, len: 0 }
+	}
+
+	fn key(&self, node: &Node) -> usize
+	{
+		match self.tie
+		{
+			Tie::Cost => node.t,
+			Tie::LowH => node.h,
+			Tie::HighG => node.g,
+			_ => 0
+		}
+	}
+
+	// Order is the one drawn for the node by the random policy
+	pub fn push(&mut self, node: Node, order: u64)
+	{
+		let (f, key) = (node.f, self.key(&node));
+		if self.len == 0
+		{
+			self.rows.clear();
+			self.base = f;
+		}
+		while f < self.base
+		{
+			self.rows.push_front(Row::default());
+			self.base -= 1;
+		}
+		if self.rows.len() <= f - self.base { self.rows.resize_with(f - self.base + 1, Row::default) }
+		let row = &mut self.rows[f - self.base];
+		if let Tie::Random(_) = self.tie { row.drawn.push(Entry { rank: 0, order, node }) }
+		else
+		{
+			if row.groups.len() <= key { row.groups.resize_with(key + 1, VecDeque::new) }
+			row.groups[key].push_back(node);
+		}
+
+		let better = match self.tie
+		{
+			Tie::HighG => key > row.best,
+			_ => key < row.best
+		};
+		if row.size == 0 || better { row.best = key }
+		row.size += 1;
+		self.len += 1;
+	}
+
+	pub fn pop(&mut self) -> Option<Node>
+	{
+		let tie = self.tie;
+		let row = self.rows.front_mut()?;
+		let node = match tie
+		{
+			Tie::Random(_) => row.drawn.pop().map(|entry| entry.node),
+			Tie::Fifo => row.groups[row.best].pop_front(),
+			_ => row.groups[row.best].pop_back()
+		};
+		row.size -= 1;
+		self.len -= 1;
+
+		if row.size > 0
+		{
+			while row.drawn.is_empty() && row.groups[row.best].is_empty()
+			{
+				match tie
+				{
+					Tie::HighG => row.best -= 1,
+					_ => row.best += 1
+				}
+			}
+		}
+		else if self.len == 0 { self.rows.clear() }
+		else
+		{
+			while self.rows.front().is_some_and(|row| row.size == 0)
+			{
+				self.rows.pop_front();
+				self.base += 1;
+			}
+		}
+		node
+	}
+
+	pub fn peek(&self) -> Option<&Node>
+	{
+		let row = self.rows.front()?;
+		match self.tie
+		{
+			Tie::Random(_) => row.drawn.peek().map(|entry| &entry.node),
+			Tie::Fifo => row.groups[row.best].front(),
+			_ => row.groups[row.best].back()
+		}
+	}
+}
+
+#[cfg(test)]
+mod tests
+{
+	use std::time::Instant;
+	use crate::Flag;
+	use crate::node::Node;
+	use crate::solver::Solver;
+	use crate::generator::Generator;
+	use crate::astar;
+	use super::{Buckets, OpenList, Queue, Tie};
+
+	// Small linear congruential generator, enough to mix pushes and pops
+	fn generator(seed: u64) -> impl FnMut(u64) -> usize
+	{
+		let mut seed = seed;
+		move |n: u64| { seed = seed.wrapping_mul(6364136223846793005).wrapping_add(1442695040888963407); ((seed >> 33) % n) as usize }
+	}
+
+	// Both queues pop the same (f, t) sequence, whatever the order of the pushes and pops
+	#[test]
+	fn same_order()
+	{
+		let mut random = generator(48);
+		let mut heap = OpenList::new(Queue::Heap, Tie::Cost);
+		let mut buckets = OpenList::new(Queue::Buckets, Tie::Cost);
+		for _ in 0..20000
+		{
+			if random(3) == 0
+			{
+				let (a, b) = (heap.pop(), buckets.pop());
+				assert_eq!(a.map(|n| (n.f, n.t)), b.map(|n| (n.f, n.t)));
+			}
+			else
+			{
+				let mut node = Node::new(vec![]);
+				node.f = random(60);
+				node.t = random(30);
+				heap.push(node.clone());
+				buckets.push(node);
+			}
+			assert_eq!(heap.len(), buckets.len());
+			assert_eq!(heap.peek().map(|n| (n.f, n.t)), buckets.peek().map(|n| (n.f, n.t)));
+		}
+
+		let mut other = OpenList::new(Queue::Buckets, Tie::Cost);
+		other.append(&mut heap);
+		assert!(heap.is_empty());
+		while let Some(node) = other.pop()
+		{
+			assert_eq!(Some((node.f, node.t)), buckets.pop().map(|n| (n.f, n.t)));
+		}
+		assert!(buckets.is_empty());
+	}
+
+	// Rows only cover the f of the nodes held, however high weighted costs get
+	#[test]
+	fn compact()
+	{
+		let mut random = generator(48);
+		let mut buckets = Buckets::new(Tie::Cost);
+		for step in 0..5000
+		{
+			// Costs climb as a search expands states, a few of them below the lowest one held
+			for _ in 0..2
+			{
+				let mut node = Node::new(vec![]);
+				node.f = 1_000_000 + step * 10 + random(40);
+				node.t = random(30);
+				buckets.push(node, 0);
+			}
+			if step > 0 { buckets.pop(); }
+			buckets.pop();
+			assert!(buckets.rows.len() <= 100);
+			assert!(buckets.rows.front().is_none_or(|row| row.size > 0));
+		}
+		assert_eq!(buckets.len, 1);
+	}
+
+	// Every policy breaks the ties the same way with both queues, nodes being told apart by their depth
+	#[test]
+	fn policies()
+	{
+		let key = |tie: Tie, node: &Node| match tie
+		{
+			Tie::LowH => (node.f, node.h),
+			Tie::HighG => (node.f, usize::MAX - node.g),
+			Tie::Lifo | Tie::Fifo | Tie::Random(_) => (node.f, node.depth),
+			_ => (node.f, 0)
+		};
+		for tie in [Tie::LowH, Tie::HighG, Tie::Lifo, Tie::Fifo, Tie::Random(7)]
+		{
+			let mut random = generator(50);
+			let (mut heap, mut buckets) = (OpenList::new(Queue::Heap, tie), OpenList::new(Queue::Buckets, tie));
+			for depth in 0..5000
+			{
+				if random(3) == 0
+				{
+					let (a, b) = (heap.pop(), buckets.pop());
+					assert_eq!(a.map(|n| key(tie, &n)), b.map(|n| key(tie, &n)));
+					continue
+				}
+				let mut node = Node::new(vec![random(16), random(16)]);
+				node.f = random(10);
+				node.h = random(node.f as u64 + 1);
+				node.g = node.f - node.h;
+				node.depth = depth;
+				heap.push(node.clone());
+				buckets.push(node);
+			}
+		}
+
+		// Nodes of equal f come out in the order they were pushed, reversed or shuffled the same on every run
+		let order = |queue: Queue, tie: Tie|
+		{
+			let mut list = OpenList::new(queue, tie);
+			for depth in 0..20
+			{
+				let mut node = Node::new(vec![depth, 0]);
+				node.depth = depth;
+				list.push(node);
+			}
+			(0..20).map(|_| list.pop().unwrap().depth).collect::<Vec<usize>>()
+		};
+		for queue in [Queue::Heap, Queue::Buckets]
+		{
+			assert_eq!(order(queue, Tie::Fifo), (0..20).collect::<Vec<usize>>());
+			assert_eq!(order(queue, Tie::Lifo), (0..20).rev().collect::<Vec<usize>>());
+			let shuffled = order(queue, Tie::Random(7));
+			assert_eq!(shuffled, order(queue, Tie::Random(7)));
+			assert_ne!(shuffled, order(queue, Tie::Random(8)));
+			assert_ne!(shuffled, (0..20).collect::<Vec<usize>>());
+		}
+	}
+
+	// Policies ordering every node expand the same states in the same order with both queues
+	#[test]
+	fn astar()
+	{
+		let mut solver = Solver::new(Generator::snail(4), 4, "conflict", Flag::default(), Instant::now());
+		let start = vec![12, 5, 14, 4, 10, 2, 11, 6, 15, 0, 3, 7, 13, 1, 9, 8];
+		for tie in [Tie::Cost, Tie::LowH, Tie::HighG, Tie::Lifo, Tie::Fifo, Tie::Random(3)]
+		{
+			solver.tie = tie;
+			solver.queue = Queue::Heap;
+			let heap = astar::find_path(start.clone(), &solver).unwrap();
+			solver.queue = Queue::Buckets;
+			let buckets = astar::find_path(start.clone(), &solver).unwrap();
+			assert_eq!(heap.moves, 32);
+			assert_eq!(buckets.moves, 32);
+			if let Tie::Lifo | Tie::Fifo | Tie::Random(_) = tie
+			{
+				assert_eq!((heap.pending, heap.selected), (buckets.pending, buckets.selected));
+				assert_eq!(heap.path, buckets.path);
+			}
+		}
+	}
+}
@@ -0,0 +1,165 @@
+use std::collections::HashMap;
+use crate::{Map, Flag};
+use crate::node::Node;
+use crate::solver::Solver;
+use crate::display::{Solution, State};
+use crate::astar_iterative_deepening;
+
+// Number of moves replaced at once when no window is given
+pub const DEFAULT_WINDOW: usize = 20;
+
+// Shorten the path of a solution when the solver has a window, keeping the number of moves
+// it had before for the report
+pub fn apply(solution: &mut Solution, solver: &Solver)
+{
+	let window = match solver.window
+	{
+		Some(window) => window,
+		None => return
+	};
+	solution.before = Some(solution.moves);
+	solution.path = optimize(std::mem::take(&mut solution.path), solver, window);
+	solution.moves = solution.path.len() - 1;
+	solution.cost = solver.path_cost(&solution.path);
+}
+
+// Remove the cycles of a path, then replace every window of 'window' moves by an optimal path
+// between its two ends, until a whole pass doesn't lower its cost. The path is in the order
+// of Solution and stays valid if the search is cancelled.
+pub fn optimize(mut path: Vec<State>, solver: &Solver, window: usize) -> Vec<State>
+{
+	let window = window.max(2);
+	loop
+	{
+		let before = cost(&path, solver);
+		path = remove_cycles(path);
+		// Windows overlap by half so that a shortcut across two of them can still be found
+		let mut i = 0;
+		while i + 1 < path.len() && !solver.cancelled()
+		{
+			let end = (i + window).min(path.len() - 1);
+			if let Some(shortcut) = shortest(&path[end].map, &path[i].map, cost(&path[i..=end], solver), solver)
+			{
+				path.splice(i..end, shortcut);
+			}
+			i += window / 2;
+		}
+		if solver.cancelled() || cost(&path, solver) >= before { return path }
+	}
+}
+
+// Cost of a path in the order of Solution, its number of moves when every move costs 1
+fn cost(path: &[State], solver: &Solver) -> usize
+{
+	solver.path_cost(path).unwrap_or(path.len() - 1)
+}
+
+// Going back to a state already visited drops the moves made in between,
+// which also removes every move undone by the next one
+pub fn remove_cycles(path: Vec<State>) -> Vec<State>
+{
+	let mut kept: Vec<State> = vec![];
+	let mut seen: HashMap<Map, usize> = HashMap::new();
+	for state in path.into_iter().rev()
+	{
+		match seen.get(&state.map)
+		{
+			Some(&index) =>
+			{
+				for dropped in kept.drain(index + 1..) { seen.remove(&dropped.map); }
+			},
+			None =>
+			{
+				seen.insert(state.map.clone(), kept.len());
+				kept.push(state);
+			}
+		}
+	}
+	kept.reverse();
+	kept
+}
+
+// Optimal path from one state to another with the rules and heuristic of the solver, in the order of
+// Solution and without the first state, if it costs less than 'limit'
+fn shortest(from: &Map, to: &Map, limit: usize, solver: &Solver) -> Option<Vec<State>>
+{
+	let mut goal = Solver::new(to.clone(), solver.size, &solver.name, Flag::default(), solver.time);
+	goal.first_cost = solver.first_cost;
+	goal.update_cost = solver.update_cost;
+	goal.rules = solver.rules.clone();
+	goal.cancel = solver.cancel.clone();
+
+	let mut start = Node::new(from.clone());
+	start.find_position(solver.size);
+	start = goal.get_cost(start);
+
+	let mut bound = start.h;
+	while bound < limit
+	{
+		let result = astar_iterative_deepening::find_path(&start, bound, &goal);
+		if result.path.is_some() { return result.path }
+		bound = result.bound;
+	}
+	None
+}
+
+#[cfg(test)]
+mod tests
+{
+	use std::time::Instant;
+	use crate::{Flag, Move, Position};
+	use crate::solver::Solver;
+	use crate::generator::Generator;
+	use crate::display::State;
+	use crate::{astar, astar_iterative_deepening};
+
+	// Each state follows from the next one with a single move
+	fn assert_valid(path: &[State], solver: &Solver)
+	{
+		assert_eq!(path.last().unwrap().movement, Move::No);
+		for step in path.windows(2)
+		{
+			let (previous, state) = (&step[1], &step[0]);
+			let blank = previous.map.iter().position(|&x| x == 0).unwrap();
+			assert_eq!(state.map, state.movement.do_move(previous.map.clone(), &Position { x: blank % solver.size, y: blank / solver.size }, solver.size));
+		}
+	}
+
+	#[test]
+	fn remove_cycles()
+	{
+		let state = |map: Vec<usize>, movement| State { map, movement };
+		// Right, Left, Right from the start comes back twice to the same states
+		let path = vec![
+			state(vec![1, 0, 2, 3], Move::Right(1)),
+			state(vec![0, 1, 2, 3], Move::Left(-1)),
+			state(vec![1, 0, 2, 3], Move::Right(1)),
+			state(vec![0, 1, 2, 3], Move::No)
+		];
+		assert_eq!(super::remove_cycles(path.clone()), path[2..].to_vec());
+	}
+
+	// A greedy solution gets shorter and stays valid, and one window over the whole path makes it optimal
+	#[test]
+	fn optimize()
+	{
+		let flag = Flag { verbosity: false, debug: false, greedy: true, uniform: false };
+		let solver = Solver::new(Generator::snail(3), 3, "manhattan", flag, Instant::now());
+		let optimal = Solver::new(Generator::snail(3), 3, "manhattan", Flag::default(), Instant::now());
+		for start in [vec![5, 4, 3, 1, 0, 2, 6, 7, 8], vec![6, 7, 1, 5, 2, 3, 4, 8, 0]]
+		{
+			let greedy = astar::find_path(start.clone(), &solver).unwrap();
+			let optimal = astar_iterative_deepening::optimal_length(start.clone(), &optimal).unwrap();
+
+			let path = super::optimize(greedy.path.clone(), &solver, 6);
+			assert!(path.len() <= greedy.path.len());
+			assert_eq!(path[0].map, Generator::snail(3));
+			assert_eq!(path.last().unwrap().map, start);
+			assert_valid(&path, &solver);
+
+			let path = super::optimize(greedy.path, &solver, 100);
+			assert_eq!(path.len() - 1, optimal);
+			assert_valid(&path, &solver);
+		}
+	}
+}
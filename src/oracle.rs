@@ -0,0 +1,45 @@
+// Exhaustive searches the tests check the heuristics and the algorithms against. Unlike table::Table they
+// follow any rules (torus, slides, weights, several blanks), at the price of a hash map of every state.
+use std::cmp::Reverse;
+use std::collections::{BinaryHeap, HashMap};
+
+use crate::{Map, Rules};
+use crate::node::Node;
+
+// Cost of the cheapest path from the start to every state it reaches, a move costing the weight of the
+// moved tile. Moves are reversible at the same cost, so these are also the costs from every state to the start.
+pub fn distances(start: &Map, size: usize, rules: &Rules) -> HashMap<Map, usize>
+{
+	let mut cost = HashMap::new();
+	search(start, size, rules, &mut cost, |_| false);
+	cost
+}
+
+// Cost of the cheapest path from the start to a state the goal accepts, None when none is reachable
+pub fn distance(start: &Map, size: usize, rules: &Rules, goal: impl Fn(&Map) -> bool) -> Option<usize>
+{
+	search(start, size, rules, &mut HashMap::new(), goal)
+}
+
+// Dijkstra's algorithm, stopping at the first state accepted by the goal
+fn search(start: &Map, size: usize, rules: &Rules, cost: &mut HashMap<Map, usize>, goal: impl Fn(&Map) -> bool) -> Option<usize>
+{
+	let mut queue = BinaryHeap::new();
+	cost.insert(start.clone(), 0);
+	queue.push(Reverse((0, start.clone())));
+	while let Some(Reverse((g, map))) = queue.pop()
+	{
+		if cost[&map] < g { continue }
+		if goal(&map) { return Some(g) }
+		let mut node = Node::new(map);
+		node.find_position(size);
+		node.g = g;
+		for child in node.generate_moves(size, rules)
+		{
+			if cost.get(&child.map).is_some_and(|&known| known <= child.g) { continue }
+			cost.insert(child.map.clone(), child.g);
+			queue.push(Reverse((child.g, child.map)));
+		}
+	}
+	None
+}
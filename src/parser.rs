@@ -1,101 +1,633 @@
 use std::fs;
+use std::io::{self, Read};
 
-use crate::Container;
+use crate::{Container, WILDCARD};
+use crate::error::{Error, Location};
 
-// Open an copy the file content into a string
-fn get_file_content(filename: &str) -> Result<String, String>
+const MAX_FILE_SIZE: u64 = 10000000;
+
+// Open an copy the file content into a string, '-' being the standard input
+fn get_file_content(filename: &str) -> Result<String, Error>
 {
-	let max_size = 10000000;
+	if filename == "-"
+	{
+		let mut content = String::new();
+		if io::stdin().take(MAX_FILE_SIZE).read_to_string(&mut content).is_err()
+		{
+			return Err(Error::Io("unable to read the standard input".to_owned()))
+		}
+		if content.trim().is_empty() { return Err(Error::Io("the standard input is empty".to_owned())) }
+		return Ok(content)
+	}
 
 	let metadata = match fs::metadata(filename)
 	{
 		Ok(m) => Ok(m),
-		Err(_) => Err(format!("'{}' doesn't exist", filename))
+		Err(_) => Err(Error::Io(format!("'{}' doesn't exist", filename)))
 	}?;
 
-	if metadata.is_file() == false { return Err(format!("'{}' is not a file", filename)) }
+	if !metadata.is_file() { return Err(Error::Io(format!("'{}' is not a file", filename))) }
 	match metadata.len()
 	{
-		size if size >= max_size => return Err(format!("'{}' is over 10MB and thus cannot be accepted", filename)),
-		0 => return Err(format!("'{}' has a size of 0", filename)),
+		size if size >= MAX_FILE_SIZE => return Err(Error::Io(format!("'{}' is over 10MB and thus cannot be accepted", filename))),
+		0 => return Err(Error::Io(format!("'{}' has a size of 0", filename))),
 		_ => ()
 	};
 
 	match fs::read_to_string(filename)
 	{
 		Ok(file) => Ok(file),
-		Err(_) => Err(format!("unable to read '{}'", filename))
+		Err(_) => Err(Error::Io(format!("unable to read '{}'", filename)))
 	}
 }
 
-fn file_to_map(file: String) -> Result<(usize, Vec<usize>), String>
+#[derive(Debug, PartialEq)]
+enum Format
 {
-	// Filter comments and empty lines
-	let mut lines: Vec<&str> = file
+	// Size on the first line followed by one line per row
+	Grid,
+	// Every tile on a single line, separated by commas or spaces
+	Permutation,
+	// Object with a 'size' and a 'tiles' array
+	Json
+}
+
+// Size, tiles with their location and whether the board is a torus
+type Parsed = (usize, Vec<(usize, Location)>, bool);
+
+// A single tile or size of the puzzle and where it was found
+struct Word<'a>
+{
+	text: &'a str,
+	at: Location
+}
+
+// Split the lines into words, without comments and empty lines
+fn filter_lines(file: &str) -> Vec<Vec<Word<'_>>>
+{
+	file
 		.lines()
-		.map(|line| line.split("#").nth(0).unwrap())
-		.map(|line| line.split("//").nth(0).unwrap())
-		.filter(|&line| line != "")
-		.map(|line| line.trim())
-		.collect();
+		.enumerate()
+		.map(|(index, line)|
+		{
+			let end = [line.find('#'), line.find("//")].iter().flatten().min().copied().unwrap_or(line.len());
+			split_words(&line[..end], index + 1)
+		})
+		.filter(|words| !words.is_empty())
+		.collect()
+}
 
-	// Get size and check if size is valid
-	let size = match lines.remove(0).parse()
+// Words are separated by commas or spaces
+fn split_words(line: &str, number: usize) -> Vec<Word<'_>>
+{
+	let mut words = vec![];
+	let mut start = None;
+	for (column, (index, c)) in line.char_indices().chain(std::iter::once((line.len(), ' '))).enumerate()
 	{
-		Ok(s) =>
+		match (c == ',' || c.is_whitespace(), start)
 		{
-			match (s >= 3, s <= 20)
+			(true, Some((begin, first))) =>
 			{
-				(false, true) => Err(format!("size is too small, must be at least 3")),
-				(true, false) => Err(format!("size is too big, must be equal or below 20")),
-				(true, true) | _ => Ok(s)
-			}
+				words.push(Word { text: &line[begin..index], at: Location { line: number, column: first } });
+				start = None;
+			},
+			(false, None) => start = Some((index, column + 1)),
+			_ => ()
 		}
-		Err(_) => Err(format!("invalid puzzle size")),
-	}?;
+	}
+	words
+}
+
+// Location right after the last word of a line
+fn end_of(words: &[Word]) -> Location
+{
+	let last = words.last().unwrap();
+	Location { line: last.at.line, column: last.at.column + last.text.chars().count() }
+}
+
+fn detect_format(file: &str) -> Result<Format, Error>
+{
+	if file.trim_start().starts_with('{') { return Ok(Format::Json) }
+	let lines = filter_lines(file);
+	match lines.len()
+	{
+		0 => Err(Error::Syntax { message: "the puzzle is empty".to_owned(), at: Location { line: 1, column: 1 } }),
+		_ if lines[0].len() == 1 => Ok(Format::Grid),
+		// Size followed by the board shape
+		n if n > 1 && lines[0].len() == 2 => Ok(Format::Grid),
+		1 => Ok(Format::Permutation),
+		_ => Err(Error::Syntax
+		{
+			message: "unable to detect the puzzle format, expected either a size followed by one line per row, \
+				all the tiles on a single line or a JSON object with 'size' and 'tiles'".to_owned(),
+			at: lines[1][0].at
+		})
+	}
+}
+
+fn parse_size(text: &str, at: Location) -> Result<usize, Error>
+{
+	match text.parse()
+	{
+		Ok(size) if (3..=1000).contains(&size) => Ok(size),
+		Ok(size) => Err(Error::Size { size, at }),
+		Err(_) => Err(Error::Syntax { message: format!("invalid puzzle size '{}'", text), at })
+	}
+}
+
+// '_' and '.' can be used for the empty tile, and '*' for a cell of a goal where any tile may end
+fn parse_tile(text: &str, at: Location, size: usize) -> Result<(usize, Location), Error>
+{
+	if text == "_" || text == "." { return Ok((0, at)) }
+	if text == "*" { return Ok((WILDCARD, at)) }
+	match text.parse()
+	{
+		Ok(n) if (n < size * size) => Ok((n, at)),
+		_ => Err(Error::Tile { tile: text.to_owned(), at })
+	}
+}
+
+// Shape of the board given in the header, true for a torus
+fn parse_board(text: &str, at: Location) -> Result<bool, Error>
+{
+	match text
+	{
+		"torus" => Ok(true),
+		"flat" => Ok(false),
+		_ => Err(Error::Syntax { message: format!("unknown board '{}', expected 'flat' or 'torus'", text), at })
+	}
+}
+
+// Check if there is duplicate numbers, blanks and wildcards apart, and that with k blanks the tiles
+// are numbered from 1 to size * size - k
+fn check_duplicates(tiles: Vec<(usize, Location)>) -> Result<Vec<usize>, Error>
+{
+	let mut number_list: Vec<bool> = vec![false; tiles.len()];
+	for (number, at) in tiles.iter().filter(|(number, _)| *number != WILDCARD)
+	{
+		match number_list[*number]
+		{
+			true if *number != 0 => { return Err(Error::Duplicate { tile: *number, at: *at }) },
+			_ => { number_list[*number] = true }
+		}
+	}
+	let last = tiles.len() - tiles.iter().filter(|(number, _)| *number == 0).count();
+	if let Some((number, at)) = tiles.iter().find(|(number, _)| *number > last && *number != WILDCARD)
+	{
+		return Err(Error::Tile { tile: number.to_string(), at: *at })
+	}
+	Ok(tiles.into_iter().map(|(number, _)| number).collect())
+}
+
+fn grid_to_map(file: &str) -> Result<Parsed, Error>
+{
+	let mut lines = filter_lines(file);
+
+	// Get size and check if size is valid, then the optional board shape
+	let first = lines.remove(0);
+	let size = parse_size(first[0].text, first[0].at)?;
+	let torus = match first.get(1)
+	{
+		Some(word) => parse_board(word.text, word.at)?,
+		None => false
+	};
 
-	// Divide each lines into words
-	let lines: Vec<Vec<&str>> = lines
-		.iter()
-		.map(|line| line.split_whitespace().collect())
-		.collect();
 	// Check if the number of rows and colums is equal to the puzzle size
-	if lines.len() != size { return Err(format!("invalid number of rows")) }
-	if lines.iter().any(|line| line.len() != size) { return Err(format!("invalid number of columns")) }
-	
+	if lines.len() != size
+	{
+		let at = match lines.get(size)
+		{
+			Some(extra) => extra[0].at,
+			None => end_of(lines.last().unwrap_or(&first))
+		};
+		return Err(Error::Rows { expected: size, found: lines.len(), at })
+	}
+	if let Some(line) = lines.iter().find(|line| line.len() != size)
+	{
+		let at = match line.get(size)
+		{
+			Some(extra) => extra.at,
+			None => end_of(line)
+		};
+		return Err(Error::Columns { expected: size, found: line.len(), at })
+	}
+
 	// Check if the tiles are positive numbers and are in range
-	let mut map: Vec<usize> = vec![];
-	for line in lines
+	let map = lines
+		.iter()
+		.flatten()
+		.map(|word| parse_tile(word.text, word.at, size))
+		.collect::<Result<Vec<(usize, Location)>, Error>>()?;
+	Ok((size, map, torus))
+}
+
+fn permutation_to_map(file: &str) -> Result<Parsed, Error>
+{
+	let words = filter_lines(file).remove(0);
+	let size = (words.len() as f64).sqrt().round() as usize;
+	if size * size != words.len()
+	{
+		return Err(Error::Syntax { message: format!("{} tiles can't form a square puzzle", words.len()), at: words[0].at })
+	}
+	if !(3..=1000).contains(&size) { return Err(Error::Size { size, at: words[0].at }) }
+	let map = words
+		.iter()
+		.map(|word| parse_tile(word.text, word.at, size))
+		.collect::<Result<Vec<(usize, Location)>, Error>>()?;
+	Ok((size, map, false))
+}
+
+// Minimal JSON reader, enough for an object of numbers and (nested) arrays of numbers
+#[derive(Debug)]
+enum Json
+{
+	Null(Location),
+	Number(String, Location),
+	Text(String, Location),
+	Array(Vec<Json>, Location),
+	Object(Vec<(String, Json)>, Location)
+}
+
+struct JsonReader<'a>
+{
+	chars: std::iter::Peekable<std::str::Chars<'a>>,
+	at: Location
+}
+
+impl<'a> JsonReader<'a>
+{
+	fn new(file: &'a str) -> Self
+	{
+		Self { chars: file.chars().peekable(), at: Location { line: 1, column: 1 } }
+	}
+
+	fn next(&mut self) -> Option<char>
+	{
+		let c = self.chars.next()?;
+		match c
+		{
+			'\n' => self.at = Location { line: self.at.line + 1, column: 1 },
+			_ => self.at.column += 1
+		};
+		Some(c)
+	}
+
+	fn error(&self, message: String) -> Error
+	{
+		Error::Syntax { message: format!("invalid JSON: {}", message), at: self.at }
+	}
+
+	fn skip_spaces(&mut self)
+	{
+		while self.chars.peek().is_some_and(|c| c.is_whitespace()) { self.next(); }
+	}
+
+	fn expect(&mut self, expected: char) -> Result<(), Error>
+	{
+		self.skip_spaces();
+		match self.chars.peek()
+		{
+			Some(&c) if c == expected => { self.next(); Ok(()) },
+			Some(&c) => Err(self.error(format!("expected '{}' but found '{}'", expected, c))),
+			None => Err(self.error(format!("expected '{}' but found the end of the input", expected)))
+		}
+	}
+
+	fn text(&mut self) -> Result<String, Error>
 	{
-		for number in line
+		self.expect('"')?;
+		let mut text = String::new();
+		loop
 		{
-			match number.parse()
+			match self.next()
 			{
-				Ok(n) if (n < size * size) => { map.push(n) },
-				Err(_) => { return Err(format!("invalid tile number: '{}'", number)) },
-				_ => { return Err(format!("tile number not in range: '{}'", number)) }
+				Some('"') => return Ok(text),
+				Some('\\') => if let Some(c) = self.next() { text.push(c) },
+				Some(c) => text.push(c),
+				None => return Err(self.error("unterminated string".to_owned()))
 			}
 		}
 	}
 
-	// Check if there is duplicate numbers
-	let mut number_list: Vec<bool> = vec![false; size * size];
-	for number in &map
+	// Read the items of an array or object until the closing character
+	fn items<T>(&mut self, close: char, mut item: impl FnMut(&mut Self) -> Result<T, Error>) -> Result<Vec<T>, Error>
 	{
-		match number_list[*number]
+		let mut items = vec![];
+		self.skip_spaces();
+		if self.chars.peek() == Some(&close)
 		{
-			true =>	{ return Err(format!("duplicate tile of '{}'", number)) },
-			false => { number_list[*number] = true }
+			self.next();
+			return Ok(items)
 		}
+		loop
+		{
+			items.push(item(self)?);
+			self.skip_spaces();
+			match self.chars.peek()
+			{
+				Some(',') => { self.next(); },
+				Some(&c) if c == close => { self.next(); return Ok(items) },
+				_ => return Err(self.error(format!("expected ',' or '{}'", close)))
+			}
+		}
+	}
+
+	fn value(&mut self) -> Result<Json, Error>
+	{
+		self.skip_spaces();
+		let at = self.at;
+		match self.chars.peek()
+		{
+			Some('{') =>
+			{
+				self.next();
+				let fields = self.items('}', |reader|
+				{
+					let key = reader.text()?;
+					reader.expect(':')?;
+					Ok((key, reader.value()?))
+				})?;
+				Ok(Json::Object(fields, at))
+			}
+			Some('[') =>
+			{
+				self.next();
+				Ok(Json::Array(self.items(']', |reader| reader.value())?, at))
+			}
+			Some('"') => Ok(Json::Text(self.text()?, at)),
+			Some(_) =>
+			{
+				let mut word = String::new();
+				while let Some(&c) = self.chars.peek()
+				{
+					if c == ',' || c == ']' || c == '}' || c.is_whitespace() { break }
+					word.push(c);
+					self.next();
+				}
+				match word.as_ref()
+				{
+					"null" => Ok(Json::Null(at)),
+					"" => Err(self.error("missing value".to_owned())),
+					_ => Ok(Json::Number(word, at))
+				}
+			}
+			None => Err(self.error("unexpected end of the input".to_owned()))
+		}
+	}
+}
+
+// 'tiles' is either a flat array or an array of rows
+fn json_tiles(tiles: &Json, size: usize) -> Result<Vec<(usize, Location)>, Error>
+{
+	let tile = |value: &Json| match value
+	{
+		Json::Number(text, at) => parse_tile(text, *at, size),
+		// Blanks and wildcards can be written as in the other formats, but tile numbers must not be quoted
+		Json::Text(text, at) if text == "_" || text == "." || text == "*" => parse_tile(text, *at, size),
+		Json::Null(at) => Ok((0, *at)),
+		Json::Text(_, at) | Json::Array(_, at) | Json::Object(_, at) =>
+			Err(Error::Syntax { message: "'tiles' must only contain numbers or rows of numbers".to_owned(), at: *at })
+	};
+
+	let (items, at) = match tiles
+	{
+		Json::Array(items, at) => (items, *at),
+		_ => return Err(Error::Syntax { message: "'tiles' must be an array".to_owned(), at: Location { line: 1, column: 1 } })
+	};
+	match items.first()
+	{
+		Some(Json::Array(..)) =>
+		{
+			if items.len() != size { return Err(Error::Rows { expected: size, found: items.len(), at }) }
+			let mut map = vec![];
+			for row in items
+			{
+				match row
+				{
+					Json::Array(row, _) if row.len() == size => for item in row { map.push(tile(item)?) },
+					Json::Array(row, at) => return Err(Error::Columns { expected: size, found: row.len(), at: *at }),
+					other => return Err(tile(other).err().unwrap_or(Error::Syntax { message: "expected a row".to_owned(), at }))
+				}
+			}
+			Ok(map)
+		},
+		_ if items.len() != size * size => Err(Error::Syntax
+		{
+			message: format!("invalid number of tiles, expected {} but found {}", size * size, items.len()),
+			at
+		}),
+		_ => items.iter().map(tile).collect()
+	}
+}
+
+fn json_to_map(file: &str) -> Result<Parsed, Error>
+{
+	let mut reader = JsonReader::new(file);
+	let (fields, at) = match reader.value()?
+	{
+		Json::Object(fields, at) => (fields, at),
+		_ => return Err(reader.error("the puzzle must be an object".to_owned()))
+	};
+	reader.skip_spaces();
+	if let Some(&c) = reader.chars.peek()
+	{
+		return Err(reader.error(format!("unexpected '{}' after the puzzle", c)))
+	}
+	let field = |name: &str| fields.iter().find(|(key, _)| key == name).map(|(_, value)| value);
+
+	let size = match field("size")
+	{
+		Some(Json::Number(text, at)) => parse_size(text, *at)?,
+		Some(_) => return Err(Error::Syntax { message: "invalid puzzle size".to_owned(), at }),
+		None => return Err(Error::Syntax { message: "the JSON puzzle has no 'size'".to_owned(), at })
+	};
+	let torus = match field("board")
+	{
+		Some(Json::Text(text, at)) => parse_board(text, *at)?,
+		Some(_) => return Err(Error::Syntax { message: "the board must be 'flat' or 'torus'".to_owned(), at }),
+		None => false
+	};
+	match field("tiles")
+	{
+		Some(tiles) => Ok((size, json_tiles(tiles, size)?, torus)),
+		None => Err(Error::Syntax { message: "the JSON puzzle has no 'tiles'".to_owned(), at })
+	}
+}
+
+fn file_to_tiles(file: &str) -> Result<Parsed, Error>
+{
+	match detect_format(file)?
+	{
+		Format::Grid => grid_to_map(file),
+		Format::Permutation => permutation_to_map(file),
+		Format::Json => json_to_map(file)
+	}
+}
+
+// Size and tiles of the puzzle, and whether its header asks for a toroidal board
+fn file_to_map(file: String) -> Result<(usize, Vec<usize>, bool), Error>
+{
+	let (size, tiles, torus) = file_to_tiles(&file)?;
+	// Only goals have wildcards
+	if let Some((_, at)) = tiles.iter().find(|(number, _)| *number == WILDCARD)
+	{
+		return Err(Error::Tile { tile: "*".to_owned(), at: *at })
 	}
-	Ok((size, map))
+	Ok((size, check_duplicates(tiles)?, torus))
+}
+
+// Goal in any of the puzzle formats, '*' marking the cells where any tile may end
+fn file_to_goal(file: String) -> Result<(usize, Vec<usize>), Error>
+{
+	let (size, tiles, _) = file_to_tiles(&file)?;
+	Ok((size, check_duplicates(tiles)?))
 }
 
-pub fn get_map(filename: &str) -> Result<Container, String>
+// Puzzle of the file and whether it is played on a torus
+pub fn get_map(filename: &str) -> Result<(Container, bool), Error>
 {
     let file = get_file_content(filename)?;
 
-	let (size, start) = file_to_map(file)?;
+	let (size, start, torus) = file_to_map(file)?;
+
+	Ok((Container(start, size), torus))
+}
+
+pub fn get_goal(filename: &str) -> Result<Container, Error>
+{
+	let file = get_file_content(filename)?;
+	let (size, goal) = file_to_goal(file)?;
+	Ok(Container(goal, size))
+}
+
+// Weights of tiles 1 to 'tiles', in any layout separated by commas or spaces,
+// returned indexed by tile number with 0 for the blank
+fn weights_from(file: &str, tiles: usize) -> Result<Vec<usize>, Error>
+{
+	let words: Vec<Word<'_>> = filter_lines(file).into_iter().flatten().collect();
+	if words.len() != tiles
+	{
+		let at = words.get(tiles).map_or(Location { line: 1, column: 1 }, |word| word.at);
+		return Err(Error::Syntax { message: format!("expected {} weights but found {}", tiles, words.len()), at })
+	}
+	let mut weights = vec![0];
+	for word in words
+	{
+		match word.text.parse()
+		{
+			Ok(weight) if weight > 0 => weights.push(weight),
+			_ => return Err(Error::Syntax { message: format!("invalid weight '{}', it must be a positive number", word.text), at: word.at })
+		}
+	}
+	Ok(weights)
+}
+
+pub fn get_weights(filename: &str, tiles: usize) -> Result<Vec<usize>, Error>
+{
+	let file = get_file_content(filename)?;
+	weights_from(&file, tiles)
+}
+
+#[cfg(test)]
+mod tests
+{
+	use crate::error::{Error, Location};
+	use crate::WILDCARD;
+	use super::{file_to_map, file_to_goal, detect_format, weights_from, Format};
+
+	fn snail() -> Vec<usize>
+	{
+		vec![1, 2, 3, 8, 0, 4, 7, 6, 5]
+	}
+
+	fn at(line: usize, column: usize) -> Location
+	{
+		Location { line, column }
+	}
+
+	#[test]
+	fn grid()
+	{
+		let file = "# comment\n3\n1 2 3 // row\n8 0 4\n  \n7 6 5\n".to_owned();
+		assert_eq!(detect_format(&file), Ok(Format::Grid));
+		assert_eq!(file_to_map(file), Ok((3, snail(), false)));
+		assert_eq!(file_to_map("3\n1 2 3\n8 _ 4\n7 6 5".to_owned()), Ok((3, snail(), false)));
+		assert_eq!(file_to_map("3\n1 2 3\n8 . 4\n7 6 5".to_owned()), Ok((3, snail(), false)));
+		assert_eq!(file_to_map("3 torus\n1 2 3\n8 0 4\n7 6 5".to_owned()), Ok((3, snail(), true)));
+		assert_eq!(file_to_map("3 flat\n1 2 3\n8 0 4\n7 6 5".to_owned()), Ok((3, snail(), false)));
+		assert_eq!(file_to_map("3 donut\n1 2 3\n8 0 4\n7 6 5".to_owned()),
+			Err(Error::Syntax { message: "unknown board 'donut', expected 'flat' or 'torus'".to_owned(), at: at(1, 3) }));
+		assert_eq!(file_to_map("3\n1 2 3\n_ _ 4\n7 6 5".to_owned()), Ok((3, vec![1, 2, 3, 0, 0, 4, 7, 6, 5], false)));
+	}
+
+	#[test]
+	fn permutation()
+	{
+		let file = "1,2,3,8,0,4,7,6,5".to_owned();
+		assert_eq!(detect_format(&file), Ok(Format::Permutation));
+		assert_eq!(file_to_map(file), Ok((3, snail(), false)));
+		assert_eq!(file_to_map("1 2 3 8 _ 4 7 6 5\n".to_owned()), Ok((3, snail(), false)));
+		assert_eq!(file_to_map("1, 2, 3, 8, 4, 7, 6, 5".to_owned()),
+			Err(Error::Syntax { message: "8 tiles can't form a square puzzle".to_owned(), at: at(1, 1) }));
+		assert_eq!(file_to_map("1,2,3,8,0,4,7,x,5".to_owned()), Err(Error::Tile { tile: "x".to_owned(), at: at(1, 15) }));
+	}
 
-	Ok(Container(start, size))
-}
\ No newline at end of file
+	#[test]
+	fn json()
+	{
+		let file = "{ \"size\": 3, \"tiles\": [1, 2, 3, 8, 0, 4, 7, 6, 5] }".to_owned();
+		assert_eq!(detect_format(&file), Ok(Format::Json));
+		assert_eq!(file_to_map(file), Ok((3, snail(), false)));
+		let file = "{\"tiles\": [[1, 2, 3], [8, null, 4], [7, 6, 5]], \"size\": 3}".to_owned();
+		assert_eq!(file_to_map(file), Ok((3, snail(), false)));
+		let file = "{\"size\": 3, \"board\": \"torus\", \"tiles\": [1, 2, 3, 8, 0, 4, 7, 6, 5]}".to_owned();
+		assert_eq!(file_to_map(file), Ok((3, snail(), true)));
+		let file = "{\"size\": 3,\n \"tiles\": [[1, 2, 3], [8, 0], [4, 7, 6, 5]]}".to_owned();
+		assert_eq!(file_to_map(file), Err(Error::Columns { expected: 3, found: 2, at: at(2, 23) }));
+		assert_eq!(file_to_map("{\"size\": 2, \"tiles\": [1, 2, 3]}".to_owned()), Err(Error::Size { size: 2, at: at(1, 10) }));
+		assert!(file_to_map("{\"size\": 3, \"tiles\": [1, 2, 3]}".to_owned()).is_err());
+		assert!(file_to_map("{\"size\": 3, \"tiles\": [1, 2, 3".to_owned()).is_err());
+		let file = "{\"size\": 3, \"tiles\": [1, 2, 3, 8, \"_\", 4, 7, 6, 5]}\n".to_owned();
+		assert_eq!(file_to_map(file), Ok((3, snail(), false)));
+		assert!(file_to_map("{\"size\": 3, \"tiles\": [\"1\", 2, 3, 8, 0, 4, 7, 6, 5]}".to_owned()).is_err());
+		let file = "{\"size\": 3, \"tiles\": [1, 2, 3, 8, 0, 4, 7, 6, 5]} trailing junk".to_owned();
+		assert_eq!(file_to_map(file), Err(Error::Syntax { message: "invalid JSON: unexpected 't' after the puzzle".to_owned(), at: at(1, 51) }));
+	}
+
+	#[test]
+	fn invalid()
+	{
+		assert!(detect_format("1 2 3\n4 5 6\n7 8 0").is_err());
+		assert!(detect_format("# only a comment").is_err());
+		assert_eq!(file_to_map("3\n1 2 3\n8 0 4\n7 6 6".to_owned()), Err(Error::Duplicate { tile: 6, at: at(4, 5) }));
+		assert_eq!(file_to_map("3\n1 2 3\n8 0 4".to_owned()), Err(Error::Rows { expected: 3, found: 2, at: at(3, 6) }));
+		assert_eq!(file_to_map("3\n1 2 3\n8 0 4 9\n7 6 5".to_owned()), Err(Error::Columns { expected: 3, found: 4, at: at(3, 7) }));
+		assert_eq!(file_to_map("3\n1 2 3\n8 0 4\n7 6 12".to_owned()), Err(Error::Tile { tile: "12".to_owned(), at: at(4, 5) }));
+		assert_eq!(file_to_map("# size\n1001\n".to_owned()), Err(Error::Size { size: 1001, at: at(2, 1) }));
+		// Two blanks leave room for tiles 1 to 7 only
+		assert_eq!(file_to_map("3\n1 2 3\n8 _ 4\n7 _ 5".to_owned()), Err(Error::Tile { tile: "8".to_owned(), at: at(3, 1) }));
+	}
+
+	#[test]
+	fn goal()
+	{
+		let w = WILDCARD;
+		assert_eq!(file_to_goal("3\n1 2 3\n* * *\n* * *".to_owned()), Ok((3, vec![1, 2, 3, w, w, w, w, w, w])));
+		assert_eq!(file_to_goal("1 * 3 * _ * 7 * *".to_owned()), Ok((3, vec![1, w, 3, w, 0, w, 7, w, w])));
+		assert_eq!(file_to_goal("{\"size\": 3, \"tiles\": [1, \"*\", 3, \"*\", null, \"*\", \"*\", \"*\", 5]}".to_owned()),
+			Ok((3, vec![1, w, 3, w, 0, w, w, w, 5])));
+		assert_eq!(file_to_goal("3\n1 2 *\n* 1 *\n* * *".to_owned()), Err(Error::Duplicate { tile: 1, at: at(3, 3) }));
+		// Only goals have wildcards
+		assert_eq!(file_to_map("3\n1 2 3\n8 0 4\n7 * 5".to_owned()), Err(Error::Tile { tile: "*".to_owned(), at: at(4, 3) }));
+	}
+
+	#[test]
+	fn weights()
+	{
+		assert_eq!(weights_from("# heavy corners\n5 1 5\n1 1\n5, 1, 5", 8), Ok(vec![0, 5, 1, 5, 1, 1, 5, 1, 5]));
+		assert_eq!(weights_from("1 2 3", 4), Err(Error::Syntax { message: "expected 4 weights but found 3".to_owned(), at: at(1, 1) }));
+		assert_eq!(weights_from("1 2 3 4 5", 4), Err(Error::Syntax { message: "expected 4 weights but found 5".to_owned(), at: at(1, 9) }));
+		assert_eq!(weights_from("1 0 3 4", 4),
+			Err(Error::Syntax { message: "invalid weight '0', it must be a positive number".to_owned(), at: at(1, 3) }));
+	}
+}
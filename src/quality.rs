@@ -0,0 +1,198 @@
+use colored::*;
+use std::hint::black_box;
+use std::time::Instant;
+
+use crate::{Map, Flag};
+use crate::node::Node;
+use crate::solver::Solver;
+use crate::generator::Generator;
+use crate::table::Table;
+use crate::check::HEURISTICS;
+use crate::error::Error;
+
+// Maximum number of rows of a histogram and width of its longest bar
+const HISTOGRAM_ROWS: usize = 16;
+const HISTOGRAM_WIDTH: usize = 40;
+
+pub struct Quality
+{
+	pub heuristic: String,
+	// Value of the heuristic on each sampled state
+	pub values: Vec<usize>,
+	// Mean of h / optimal distance, over the states whose distance is known and not 0
+	pub ratio: Option<f64>,
+	// Mean evaluation time of a full computation and of an incremental update after a move
+	pub full_ns: f64,
+	pub incremental_ns: f64
+}
+
+impl Quality
+{
+	pub fn mean(&self) -> f64
+	{
+		mean(&self.values)
+	}
+}
+
+fn mean(values: &[usize]) -> f64
+{
+	match values.len()
+	{
+		0 => 0.0,
+		n => values.iter().sum::<usize>() as f64 / n as f64
+	}
+}
+
+// Evaluate a heuristic on every state, with the optimal distance of the state when known
+pub fn measure(heuristic: &str, goal: &Map, size: usize, states: &[Map], distances: &[Option<usize>]) -> Quality
+{
+	let solver = Solver::new(goal.clone(), size, heuristic, Flag::default(), Instant::now());
+	let nodes: Vec<Node> = states
+		.iter()
+		.map(|map| { let mut node = Node::new(map.clone()); node.find_position(size); node })
+		.collect();
+
+	let inputs = nodes.clone();
+	let time = Instant::now();
+	let evaluated: Vec<Node> = inputs.into_iter().map(|node| black_box((solver.first_cost)(node, &solver.end, size))).collect();
+	let full_ns = time.elapsed().as_nanos() as f64 / nodes.len().max(1) as f64;
+
+	let children: Vec<Node> = evaluated.iter().flat_map(|node| node.generate_moves(size, &solver.rules)).collect();
+	let count = children.len().max(1);
+	let time = Instant::now();
+	for child in children
+	{
+		black_box((solver.update_cost)(child, &solver.end, size));
+	}
+	let incremental_ns = time.elapsed().as_nanos() as f64 / count as f64;
+
+	let values: Vec<usize> = evaluated.iter().map(|node| node.h).collect();
+	let ratios: Vec<f64> = values
+		.iter()
+		.zip(distances)
+		.filter_map(|(&h, distance)| match distance
+		{
+			Some(d) if *d > 0 => Some(h as f64 / *d as f64),
+			_ => None
+		})
+		.collect();
+	let ratio = match ratios.len()
+	{
+		0 => None,
+		n => Some(ratios.iter().sum::<f64>() / n as f64)
+	};
+	Quality { heuristic: heuristic.to_owned(), values, ratio, full_ns, incremental_ns }
+}
+
+// Rows of (lowest value, highest value, number of values) covering every value from the minimum to the maximum
+pub fn histogram(values: &[usize]) -> Vec<(usize, usize, usize)>
+{
+	let (min, max) = match (values.iter().min(), values.iter().max())
+	{
+		(Some(&min), Some(&max)) => (min, max),
+		_ => return vec![]
+	};
+	let width = (max - min) / HISTOGRAM_ROWS + 1;
+	let mut rows: Vec<(usize, usize, usize)> = (min..=max)
+		.step_by(width)
+		.map(|low| (low, (low + width - 1).min(max), 0))
+		.collect();
+	for value in values
+	{
+		rows[(value - min) / width].2 += 1;
+	}
+	rows
+}
+
+fn display_histogram(values: &[usize])
+{
+	let rows = histogram(values);
+	let highest = rows.iter().map(|row| row.2).max().unwrap_or(0).max(1);
+	for (low, high, count) in rows
+	{
+		let range = match low == high
+		{
+			true => low.to_string(),
+			false => format!("{}-{}", low, high)
+		};
+		let bar = "#".repeat((count * HISTOGRAM_WIDTH).div_ceil(highest));
+		println!("    {:>9} | {} {}", range, bar.cyan(), count);
+	}
+}
+
+// Compare every heuristic on random solvable states, against the exact distances on 3x3 boards
+pub fn run(size: usize, goal: &str, samples: usize) -> Result<(), Error>
+{
+	let goal_map = Generator::generate_goal(goal, size);
+	let mut generator = Generator::new(size, None, "random", goal, ".", None);
+	let states: Vec<Map> = (0..samples).map(|_| generator.random_map(&goal_map, true)).collect();
+	let distances: Vec<Option<usize>> = match size
+	{
+		3 =>
+		{
+			let table = Table::new(&goal_map);
+			states.iter().map(|map| table.distance(map)).collect()
+		},
+		_ => vec![None; states.len()]
+	};
+	let known: Vec<usize> = distances.iter().filter_map(|&d| d).collect();
+
+	println!("Measuring {} random states of size {} (seed {})", states.len().to_string().cyan(), size, generator.seed);
+	if !known.is_empty()
+	{
+		println!("Mean optimal distance: {}", format!("{:.2}", mean(&known)).green());
+	}
+	for heuristic in HEURISTICS.iter()
+	{
+		let quality = measure(heuristic, &goal_map, size, &states, &distances);
+		println!("{}", quality.heuristic.bold());
+		let ratio = match quality.ratio
+		{
+			Some(ratio) => format!("{:.3}", ratio),
+			None => "unknown".to_owned()
+		};
+		println!("  mean h: {}, ratio to the optimal distance: {}", format!("{:.2}", quality.mean()).green(), ratio.yellow());
+		println!("  evaluation: {} ns per full call, {} ns per incremental call",
+			format!("{:.0}", quality.full_ns).magenta(), format!("{:.0}", quality.incremental_ns).magenta());
+		display_histogram(&quality.values);
+	}
+	Ok(())
+}
+
+#[cfg(test)]
+mod tests
+{
+	use crate::generator::Generator;
+	use crate::table::Table;
+	use super::{measure, histogram};
+
+	#[test]
+	fn histogram_rows()
+	{
+		assert_eq!(histogram(&[]), vec![]);
+		assert_eq!(histogram(&[4, 4]), vec![(4, 4, 2)]);
+		assert_eq!(histogram(&[1, 3, 3, 5]), vec![(1, 1, 1), (2, 2, 0), (3, 3, 2), (4, 4, 0), (5, 5, 1)]);
+		// 40 values need buckets of 3 to fit within 16 rows
+		let rows = histogram(&(0..40).collect::<Vec<usize>>());
+		assert_eq!(rows.len(), 14);
+		assert_eq!(rows[0], (0, 2, 3));
+		assert_eq!(rows[13], (39, 39, 1));
+		assert_eq!(rows.iter().map(|row| row.2).sum::<usize>(), 40);
+	}
+
+	#[test]
+	fn ordered_heuristics()
+	{
+		let goal = Generator::snail(3);
+		let table = Table::new(&goal);
+		let states = table.states(10, 20);
+		let distances: Vec<Option<usize>> = states.iter().map(|map| table.distance(map)).collect();
+		let manhattan = measure("manhattan", &goal, 3, &states, &distances);
+		let misplaced = measure("misplaced", &goal, 3, &states, &distances);
+
+		assert_eq!(manhattan.values.len(), states.len());
+		assert!(manhattan.mean() > misplaced.mean());
+		let (manhattan, misplaced) = (manhattan.ratio.unwrap(), misplaced.ratio.unwrap());
+		assert!(misplaced < manhattan && manhattan <= 1.0);
+	}
+}
@@ -0,0 +1,389 @@
+use std::collections::VecDeque;
+use crate::{Map, Move, Container};
+use crate::solver::Solver;
+use crate::error::Error;
+use crate::table::Table;
+use crate::observer::Event;
+use crate::display::{Solution, State};
+
+// Extra cells around the blank and its target searched before falling back to the whole board
+const ROUTE_MARGIN: usize = 2;
+
+// Rows [top, bottom] and columns [left, right] of the board that are not solved yet
+#[derive(Clone, Copy)]
+struct Region
+{
+	top: usize,
+	bottom: usize,
+	left: usize,
+	right: usize
+}
+
+impl Region
+{
+	fn height(&self) -> usize { self.bottom - self.top + 1 }
+	fn width(&self) -> usize { self.right - self.left + 1 }
+}
+
+struct Board<'a>
+{
+	map: Map,
+	size: usize,
+	blank: usize,
+	position: Vec<usize>,
+	locked: Vec<bool>,
+	moves: Vec<Move>,
+	goal: &'a Map,
+	end: &'a Map
+}
+
+impl<'a> Board<'a>
+{
+	fn new(map: Map, solver: &'a Solver) -> Self
+	{
+		let blank = map.iter().position(|&x| x == 0).unwrap();
+		Self
+		{
+			locked: vec![false; map.len()],
+			position: Container(map.clone(), solver.size).swap_indexes(),
+			size: solver.size,
+			blank,
+			map,
+			moves: vec![],
+			goal: &solver.goal,
+			end: &solver.end
+		}
+	}
+
+	fn neighbours(&self, index: usize) -> impl Iterator<Item = usize>
+	{
+		let (x, y, size) = (index % self.size, index / self.size, self.size);
+		IntoIterator::into_iter([
+			if x > 0 { Some(index - 1) } else { None },
+			if x < size - 1 { Some(index + 1) } else { None },
+			if y > 0 { Some(index - size) } else { None },
+			if y < size - 1 { Some(index + size) } else { None }
+		]).flatten()
+	}
+
+	// Path going straight along one axis then the other, if none of its cells is taken
+	fn straight(&self, from: usize, to: usize, avoid: usize, horizontal_first: bool) -> Option<Vec<usize>>
+	{
+		let (mut x, mut y) = (from % self.size, from / self.size);
+		let (tx, ty) = (to % self.size, to / self.size);
+		let mut path = vec![];
+		for horizontal in [horizontal_first, !horizontal_first]
+		{
+			while (horizontal && x != tx) || (!horizontal && y != ty)
+			{
+				match horizontal
+				{
+					true => if x < tx { x += 1 } else { x -= 1 },
+					false => if y < ty { y += 1 } else { y -= 1 }
+				}
+				let cell = y * self.size + x;
+				if self.locked[cell] || cell == avoid { return None }
+				path.push(cell);
+			}
+		}
+		Some(path)
+	}
+
+	// Straight paths are tried first since searching a wide window is slow on big boards
+	fn find_route(&self, from: usize, to: usize, avoid: usize) -> Option<Vec<usize>>
+	{
+		self.straight(from, to, avoid, true)
+			.or_else(|| self.straight(from, to, avoid, false))
+			.or_else(|| self.route(from, to, avoid, Some(ROUTE_MARGIN)))
+			.or_else(|| self.route(from, to, avoid, None))
+	}
+
+	// Slide the blank to an adjacent cell
+	fn step(&mut self, to: usize)
+	{
+		let movement = match to as i64 - self.blank as i64
+		{
+			-1 => Move::Left(-1),
+			1 => Move::Right(1),
+			offset if offset < 0 => Move::Up(offset),
+			offset => Move::Down(offset)
+		};
+		self.position[self.map[to]] = self.blank;
+		self.map.swap(self.blank, to);
+		self.blank = to;
+		self.moves.push(movement);
+	}
+
+	// Shortest path of free cells between two cells, optionally kept inside a window around them.
+	// Only the cells of the window are allocated so that short routes stay cheap on big boards.
+	fn route(&self, from: usize, to: usize, avoid: usize, margin: Option<usize>) -> Option<Vec<usize>>
+	{
+		let (ax, ay, bx, by) = (from % self.size, from / self.size, to % self.size, to / self.size);
+		let (x0, y0, x1, y1) = match margin
+		{
+			Some(m) => (ax.min(bx).saturating_sub(m), ay.min(by).saturating_sub(m),
+				(ax.max(bx) + m).min(self.size - 1), (ay.max(by) + m).min(self.size - 1)),
+			None => (0, 0, self.size - 1, self.size - 1)
+		};
+		let width = x1 - x0 + 1;
+		let local = |index: usize| (index / self.size - y0) * width + index % self.size - x0;
+		let inside = |index: usize|
+		{
+			let (x, y) = (index % self.size, index / self.size);
+			x0 <= x && x <= x1 && y0 <= y && y <= y1
+		};
+
+		let mut previous: Vec<Option<usize>> = vec![None; width * (y1 - y0 + 1)];
+		let mut queue = VecDeque::new();
+		previous[local(from)] = Some(from);
+		queue.push_back(from);
+		while let Some(current) = queue.pop_front()
+		{
+			if current == to
+			{
+				let mut path = vec![];
+				let mut cell = to;
+				while cell != from
+				{
+					path.push(cell);
+					cell = previous[local(cell)].unwrap();
+				}
+				path.reverse();
+				return Some(path)
+			}
+			for next in self.neighbours(current)
+			{
+				if !inside(next) || self.locked[next] || next == avoid || previous[local(next)].is_some() { continue }
+				previous[local(next)] = Some(current);
+				queue.push_back(next);
+			}
+		}
+		None
+	}
+
+	// Bring the blank to a cell without going through the given cell
+	fn move_blank(&mut self, to: usize, avoid: usize) -> Result<(), Error>
+	{
+		match self.find_route(self.blank, to, avoid)
+		{
+			Some(path) => { path.into_iter().for_each(|cell| self.step(cell)); Ok(()) },
+			None => Err(Error::Internal("the reduction solver was unable to move the empty tile".to_owned()))
+		}
+	}
+
+	// Push a tile along the shortest free path to a cell, one blank rotation per step
+	fn move_tile(&mut self, tile: usize, to: usize) -> Result<(), Error>
+	{
+		let from = self.position[tile];
+		let path = match self.find_route(from, to, self.map.len())
+		{
+			Some(path) => path,
+			None => return Err(Error::Internal(format!("the reduction solver was unable to move tile {}", tile)))
+		};
+		let mut current = from;
+		for next in path
+		{
+			self.move_blank(next, current)?;
+			self.step(current);
+			current = next;
+		}
+		Ok(())
+	}
+
+	// Solve a line of cells at the edge of the region, 'inside' being the offset
+	// from the line toward the rest of the region.
+	// The last two tiles can't be placed one after the other, so the last one is
+	// first parked in the second to last cell with the other one under it, then
+	// both are rotated into place. The other tile waits one more cell away while the
+	// last one is parked, otherwise it could be stuck in the corner of the line.
+	fn solve_line(&mut self, line: &[usize], inside: i64) -> Result<(), Error>
+	{
+		let count = line.len();
+		for &cell in &line[..count - 2]
+		{
+			self.move_tile(self.goal[cell], cell)?;
+			self.locked[cell] = true;
+		}
+
+		let (before, last) = (line[count - 2], line[count - 1]);
+		let (tile_before, tile_last) = (self.goal[before], self.goal[last]);
+		if self.map[before] != tile_before || self.map[last] != tile_last
+		{
+			let under = (before as i64 + inside) as usize;
+			let below = (under as i64 + inside) as usize;
+			self.move_tile(tile_before, below)?;
+			self.locked[below] = true;
+			self.move_tile(tile_last, before)?;
+			self.locked[before] = true;
+			self.locked[below] = false;
+			self.move_tile(tile_before, under)?;
+			self.locked[under] = true;
+			self.move_blank(last, self.map.len())?;
+			self.locked[before] = false;
+			self.locked[under] = false;
+			self.step(before);
+			self.step(under);
+		}
+		self.locked[before] = true;
+		self.locked[last] = true;
+		Ok(())
+	}
+
+	// Solve the last 3x3 region optimally with the complete table of its states
+	fn solve_last(&mut self, region: Region) -> Result<(), Error>
+	{
+		let size = self.size;
+		let cells: Vec<usize> = (region.top..=region.bottom)
+			.flat_map(|y| (region.left..=region.right).map(move |x| y * size + x))
+			.collect();
+		let mut tiles: Vec<usize> = cells.iter().map(|&cell| self.goal[cell]).collect();
+		tiles.sort();
+		let label = |tile: usize| tiles.iter().position(|&t| t == tile).unwrap();
+		let goal: Map = cells.iter().map(|&cell| label(self.goal[cell])).collect();
+		let start: Map = cells.iter().map(|&cell| label(self.map[cell])).collect();
+
+		let path = match Table::new(&goal).path(&start)
+		{
+			Some(path) => path,
+			None => return Err(Error::Unsolvable)
+		};
+		for state in path.iter().rev().skip(1)
+		{
+			let to = match state.movement
+			{
+				Move::Left(_) => self.blank - 1,
+				Move::Right(_) => self.blank + 1,
+				Move::Up(_) => self.blank - self.size,
+				Move::Down(_) => self.blank + self.size,
+				Move::No => self.blank
+			};
+			self.step(to);
+		}
+		Ok(())
+	}
+
+	// Peel rows and columns off the region until only a 3x3 square remains around
+	// the goal position of the empty tile. The longest side is reduced first.
+	fn solve(&mut self) -> Result<(), Error>
+	{
+		let mut region = Region { top: 0, bottom: self.size - 1, left: 0, right: self.size - 1 };
+		let blank_goal = self.end[0];
+		let (blank_x, blank_y) = (blank_goal % self.size, blank_goal / self.size);
+
+		while region.height() > 3 || region.width() > 3
+		{
+			let size = self.size;
+			let rows = |y: usize| (region.left..=region.right).map(|x| y * size + x).collect::<Vec<usize>>();
+			let columns = |x: usize| (region.top..=region.bottom).map(|y| y * size + x).collect::<Vec<usize>>();
+			if region.height() >= region.width()
+			{
+				if blank_y != region.top
+				{
+					self.solve_line(&rows(region.top), self.size as i64)?;
+					region.top += 1;
+				}
+				else
+				{
+					self.solve_line(&rows(region.bottom), -(self.size as i64))?;
+					region.bottom -= 1;
+				}
+			}
+			else if blank_x != region.left
+			{
+				self.solve_line(&columns(region.left), 1)?;
+				region.left += 1;
+			}
+			else
+			{
+				self.solve_line(&columns(region.right), -1)?;
+				region.right -= 1;
+			}
+		}
+		self.solve_last(region)
+	}
+}
+
+// Non optimal solver for boards of any size: rows and columns are solved one at a time
+// with tile moves that never disturb the tiles already placed, then the last 3x3
+// region is solved optimally.
+pub fn find_path(start: Map, solver: &Solver) -> Result<Solution, Error>
+{
+	let mut board = Board::new(start.clone(), solver);
+	board.solve()?;
+	if board.map != solver.goal { return Err(Error::Internal("the reduction solver failed to reach the goal".to_owned())) }
+
+	let mut solution = Solution::new(0, 0);
+	solution.moves = board.moves.len();
+	// States are only kept when they are displayed since big boards can take millions of moves
+	if solver.flag.verbosity
+	{
+		let mut map = start;
+		let mut blank = map.iter().position(|&x| x == 0).unwrap();
+		solution.path.push(State { map: map.clone(), movement: Move::No });
+		for movement in board.moves
+		{
+			let next = (blank as i64 + movement.get_offset()) as usize;
+			map.swap(blank, next);
+			blank = next;
+			solution.path.push(State { map: map.clone(), movement });
+		}
+		solution.path.reverse();
+	}
+	solver.notify(Event::Solution { moves: solution.moves });
+	Ok(solution)
+}
+
+pub fn solve(start: Map, solver: Solver) -> Result<(), Error>
+{
+	let mut solution = find_path(start, &solver)?;
+	solution.display(solver.size, solver.flag.verbosity, solver.time);
+	Ok(())
+}
+
+#[cfg(test)]
+mod tests
+{
+	use std::time::Instant;
+	use crate::Flag;
+	use crate::solver::Solver;
+	use crate::generator::Generator;
+
+	fn solve(size: usize, goal: &str, seed: u64)
+	{
+		let end = Generator::generate_goal(goal, size);
+		let mut generator = Generator::new(size, None, "random", goal, ".", Some(seed));
+		let start = generator.random_map(&end, true);
+		let solver = Solver::new(end.clone(), size, "manhattan", Flag::default(), Instant::now());
+		let solution = super::find_path(start, &solver).unwrap();
+		assert!(solution.moves > 0);
+	}
+
+	#[test]
+	fn every_goal()
+	{
+		for goal in &["snail", "classic", "reversed"]
+		{
+			for size in 3..8 { solve(size, goal, size as u64) }
+		}
+	}
+
+	#[test]
+	fn big_board()
+	{
+		solve(20, "snail", 1);
+	}
+
+	#[test]
+	fn replayed_path()
+	{
+		let end = Generator::classic(4);
+		let mut generator = Generator::new(4, None, "random", "classic", ".", Some(3));
+		let start = generator.random_map(&end, true);
+		let flag = Flag { verbosity: true, ..Flag::default() };
+		let solver = Solver::new(end.clone(), 4, "manhattan", flag, Instant::now());
+		let solution = super::find_path(start.clone(), &solver).unwrap();
+		assert_eq!(solution.path.len(), solution.moves + 1);
+		assert_eq!(solution.path[0].map, end);
+		assert_eq!(solution.path.last().unwrap().map, start);
+	}
+}
@@ -1,7 +1,13 @@
-use crate::{Map, Flag, Container};
+use crate::{Map, Flag, Container, Rules, WILDCARD};
 use crate::heuristic;
 use crate::node::Node;
+use crate::error::Error;
+use crate::display::{Info, State};
+use crate::observer::{Event, Observer};
+use crate::open_list::{Queue, Tie};
 use std::time::{Instant};
+use std::sync::{Arc, Mutex};
+use std::sync::atomic::{AtomicBool, Ordering};
 
 pub struct Solver
 {
@@ -12,7 +18,22 @@ pub struct Solver
 	pub first_cost: fn(Node, &Map, usize) -> Node,
 	pub update_cost: fn(Node, &Map, usize) -> Node,
 	pub flag: Flag,
-	pub time: Instant
+	pub time: Instant,
+	pub threads: usize,
+	// Number of moves of the windows replaced by the optimizer, None to keep the path found
+	pub window: Option<usize>,
+	// Priority queue of the open states of A*, IA* and ILA*
+	pub queue: Queue,
+	// Which open state of equal f the searches with an open list expand first
+	pub tie: Tie,
+	// Closed sets of A*, IA* and ILA* also keep the states, so that two states of equal Zobrist hashes are told apart
+	pub confirm: bool,
+	// Shape of the board and cost of the moves
+	pub rules: Rules,
+	// Raised from outside (Ctrl-C) to stop the search and report its progress
+	pub cancel: Arc<AtomicBool>,
+	// Receives the progress of the search, a progress bar by default
+	pub observer: Mutex<Box<dyn Observer + Send>>
 }
 
 impl Solver
@@ -38,7 +59,7 @@ impl Solver
 				first_cost = heuristic::linear_conflict;
 				update_cost = heuristic::partial_conflict;
 			}
-			"manhattan" | _ =>
+			_ =>
 			{
 				first_cost = heuristic::manhattan;
 				update_cost = heuristic::partial_manhattan;
@@ -50,51 +71,129 @@ impl Solver
 		{
 			end: container.swap_indexes(),
 			goal: container.0,
-			size: size,
-			first_cost: first_cost,
-			update_cost: update_cost,
+			size,
+			first_cost,
+			update_cost,
 			name: name.to_owned(),
-			flag: flag,
-			time: time
+			flag,
+			time,
+			threads: 1,
+			window: None,
+			queue: Queue::default(),
+			tie: Tie::default(),
+			confirm: false,
+			rules: Rules::default(),
+			cancel: Arc::new(AtomicBool::new(false)),
+			observer: Mutex::new(Box::new(Info::new(0)))
 		}
 	}
 
-	// The solvability of a puzzle is explaned here (including inversions):
-	// http://www.cs.bham.ac.uk/~mdr/teaching/modules04/java2/TilesSolvability.html
-	pub fn is_solvable(&self, start: &Map) -> Result<(), String>
+	// Play on a toroidal board, with a manhattan distance going across the edges.
+	// Linear conflicts assume that a tile can't go around the others and are refused.
+	pub fn set_torus(&mut self) -> Result<(), Error>
 	{
-		// Returns the sum of inversions for each tiles except the empty one
-		fn get_inversions(map: &Map) -> usize
+		match self.name.as_ref()
 		{
-			let mut inversions = 0;
-			for i in 0..map.len() - 1
+			"conflict" => return Err(Error::Argument("linear conflicts don't apply to a torus, choose another heuristic".to_owned())),
+			"manhattan" =>
 			{
-				for j in i + 1..map.len()
-				{
-					if map[i] == 0 || map[j] == 0 { continue }
-					if map[i] > map[j] { inversions += 1 }
-				}
-			}
-			return inversions;
+				self.first_cost = heuristic::torus_manhattan;
+				self.update_cost = heuristic::partial_torus_manhattan;
+			},
+			_ => ()
+		};
+		self.rules.torus = true;
+		Ok(())
+	}
+
+	// Make moving tile t cost weights[t], h becoming the sum of the costs of each tile times its weight.
+	// A linear conflict can be solved by moving either tile, so its weight can't be told and it is refused.
+	pub fn set_weights(&mut self, weights: Vec<usize>) -> Result<(), Error>
+	{
+		if self.name == "conflict"
+		{
+			return Err(Error::Argument("linear conflicts don't apply to weighted moves, choose another heuristic".to_owned()))
 		}
+		self.rules.weights = Some(weights);
+		Ok(())
+	}
 
-		let mut start_inv = get_inversions(start);
-		let mut end_inv = get_inversions(&self.goal);
-		// If the size is even, we take into account the position of the empty tile
-		if self.size % 2 == 0
+	// Count a slide of several tiles along a row or a column as a single move (multi-tile metric).
+	// Linear conflicts would need their own bound, and a slide has no single tile to weigh or torus edge to cross.
+	pub fn set_slides(&mut self) -> Result<(), Error>
+	{
+		let cost: fn(Node, &Map, usize) -> Node = match self.name.as_ref()
+		{
+			"conflict" => return Err(Error::Argument("linear conflicts don't apply to the multi-tile metric, choose another heuristic".to_owned())),
+			"misplaced" => heuristic::slide_misplaced,
+			"axes" => heuristic::slide_out_of_axes,
+			_ => heuristic::slide_manhattan
+		};
+		if self.rules.torus || self.rules.weights.is_some()
 		{
-			start_inv += start.iter().position(|x| *x == 0).unwrap() / self.size;
-			end_inv += self.goal.iter().position(|x| *x == 0).unwrap() / self.size;
+			return Err(Error::Argument("the multi-tile metric only applies to flat puzzles whose moves all cost 1".to_owned()))
 		}
-		// The "total" polarity (depending on the polarity of the size)
-		// of a solvable puzzle MUST be the same as that of its final state
-		match start_inv % 2 == end_inv % 2
+		self.first_cost = cost;
+		self.update_cost = cost;
+		self.rules.slides = true;
+		Ok(())
+	}
+
+	// Total cost of a path in the order of display::Solution, None when every move costs 1
+	pub fn path_cost(&self, path: &[State]) -> Option<usize>
+	{
+		self.rules.weights.as_ref()?;
+		let cost = path
+			.windows(2)
+			.filter_map(|step| (0..step[0].map.len()).find(|&i| step[1].map[i] == 0 && step[0].map[i] != 0).map(|i| step[0].map[i]))
+			.map(|tile| self.rules.cost(tile))
+			.sum();
+		Some(cost)
+	}
+
+	// Heuristic of a node whose costs per tile are scaled by the weights of the tiles
+	fn weighted(&self, mut node: Node) -> Node
+	{
+		if let Some(weights) = &self.rules.weights
+		{
+			node.h = weights.iter().zip(&node.cost).map(|(w, cost)| w * cost).sum();
+		}
+		node
+	}
+
+	pub fn cancelled(&self) -> bool
+	{
+		self.cancel.load(Ordering::Relaxed)
+	}
+
+	pub fn notify(&self, event: Event)
+	{
+		self.observer.lock().unwrap().notify(event);
+	}
+
+	// A partial goal can be reached when one of the full goals it stands for can
+	pub fn is_solvable(&self, start: &Map) -> Result<(), Error>
+	{
+		match completions(start, &self.goal).iter().any(|goal| is_solvable(start, goal, self.size, self.rules.torus))
 		{
 			true => Ok(()),
-			false => Err("unsolvable puzzle".to_owned())
+			false => Err(Error::Unsolvable)
 		}
 	}
 
+	// Every tile of the goal is in place, wildcards apart
+	pub fn matches(&self, map: &Map) -> bool
+	{
+		self.goal.iter().zip(map).all(|(&goal, &tile)| goal == WILDCARD || goal == tile)
+	}
+
+	// Every heuristic gives 0 on the goal, so the state is only compared when h is 0.
+	// Searches stop at the first state matching a partial goal, whatever its free tiles.
+	pub fn is_goal(&self, node: &Node) -> bool
+	{
+		node.h == 0 && self.matches(&node.map)
+	}
+
 	pub fn get_cost(&self, mut node: Node) -> Node
 	{
 		if self.flag.uniform
@@ -104,6 +203,7 @@ impl Solver
 			return node;
 		}
 		node = (self.first_cost)(node, &self.end, self.size);
+		node = self.weighted(node);
 		match self.flag.greedy
 		{
 			true => { node.f = node.h; node.t = node.g },
@@ -117,10 +217,21 @@ impl Solver
 		if self.flag.uniform
 		{
 			node.f = node.g;
-			if node.map == self.goal { node.h = 0 }
+			if self.matches(&node.map) { node.h = 0 }
 			return node;
 		}
+		// Only the cost of the moved tile changes, h moves by its difference times the weight of the tile
+		let weighted = self.rules.weights.as_ref().map(|_|
+		{
+			let tile = node.map[node.pos.moved_element(&node.movement, self.size).as_index(self.size)];
+			(tile, node.h, node.cost[tile])
+		});
 		node = (self.update_cost)(node, &self.end, self.size);
+		if let Some((tile, h, previous)) = weighted
+		{
+			let weight = self.rules.cost(tile);
+			node.h = h + weight * node.cost[tile] - weight * previous;
+		}
 		match self.flag.greedy
 		{
 			true => { node.f = node.h; node.t = node.g },
@@ -128,4 +239,208 @@ impl Solver
 		};
 		node
 	}
-}
\ No newline at end of file
+}
+
+// Full goals standing for a partial goal, enough to tell whether it can be reached: the tiles of the start
+// missing from the goal fill its wildcards in order, then swapping two of them flips the parity.
+// With a single free tile, the other full goal has it swapped with the blank.
+fn completions(start: &Map, goal: &Map) -> Vec<Map>
+{
+	let cells: Vec<usize> = (0..goal.len()).filter(|&i| goal[i] == WILDCARD).collect();
+	if cells.is_empty() { return vec![goal.clone()] }
+	let mut missing = start.clone();
+	for tile in goal.iter().filter(|&&tile| tile != WILDCARD)
+	{
+		if let Some(index) = missing.iter().position(|x| x == tile) { missing.swap_remove(index); }
+	}
+	missing.sort_unstable();
+	let mut completed = goal.clone();
+	for (&cell, tile) in cells.iter().zip(missing)
+	{
+		completed[cell] = tile;
+	}
+	let tiles: Vec<usize> = cells.iter().copied().filter(|&cell| completed[cell] != 0).collect();
+	let mut swapped = completed.clone();
+	match (tiles.len(), cells.len())
+	{
+		(n, _) if n >= 2 => swapped.swap(tiles[0], tiles[1]),
+		(_, 2) => swapped.swap(cells[0], cells[1]),
+		_ => return vec![completed]
+	};
+	vec![completed, swapped]
+}
+
+// The solvability of a puzzle is explaned here (including inversions):
+// http://www.cs.bham.ac.uk/~mdr/teaching/modules04/java2/TilesSolvability.html
+#[allow(clippy::manual_is_multiple_of)]
+pub fn is_solvable(start: &Map, goal: &Map, size: usize, torus: bool) -> bool
+{
+	// With a second blank any two tiles can be swapped, so every arrangement is reachable
+	if start.iter().filter(|&&x| x == 0).count() > 1 { return true }
+	// Going around an odd row of a torus brings the blank back with an odd permutation of the tiles,
+	// so every arrangement is reachable. Even tori keep the parity rule of the flat board.
+	if torus && size % 2 == 1 { return true }
+
+	// Returns the sum of inversions for each tiles except the empty one
+	fn get_inversions(map: &Map) -> usize
+	{
+		let mut inversions = 0;
+		for i in 0..map.len() - 1
+		{
+			for j in i + 1..map.len()
+			{
+				if map[i] == 0 || map[j] == 0 { continue }
+				if map[i] > map[j] { inversions += 1 }
+			}
+		}
+		inversions
+	}
+
+	let mut start_inv = get_inversions(start);
+	let mut end_inv = get_inversions(goal);
+	// If the size is even, we take into account the position of the empty tile
+	if size % 2 == 0
+	{
+		start_inv += start.iter().position(|x| *x == 0).unwrap() / size;
+		end_inv += goal.iter().position(|x| *x == 0).unwrap() / size;
+	}
+	// The "total" polarity (depending on the polarity of the size)
+	// of a solvable puzzle MUST be the same as that of its final state
+	start_inv % 2 == end_inv % 2
+}
+
+#[cfg(test)]
+mod tests
+{
+	use std::time::Instant;
+	use crate::{Flag, Position, Rules, WILDCARD};
+	use crate::node::Node;
+	use crate::generator::Generator;
+	use crate::{astar, astar_hash_distributed, astar_iterative_deepening, oracle};
+	use super::{completions, is_solvable, Solver};
+
+	const W: usize = WILDCARD;
+
+	// With two blanks the parity constraint disappears and every arrangement can reach the goal
+	#[test]
+	fn two_blanks()
+	{
+		let goal = Generator::with_blanks(Generator::snail(3), 2);
+		assert_eq!(goal, vec![1, 2, 3, 0, 0, 4, 7, 6, 5]);
+		let reachable = oracle::distances(&goal, 3, &Rules::default());
+		// 9! / 2! arrangements of 7 tiles and 2 blanks
+		assert_eq!(reachable.len(), 181440);
+		let swapped = vec![2, 1, 3, 0, 0, 4, 7, 6, 5];
+		assert!(reachable.contains_key(&swapped));
+		assert!(is_solvable(&swapped, &goal, 3, false));
+	}
+
+	// Going around an odd torus swaps two tiles, so every arrangement can reach the goal
+	#[test]
+	fn odd_torus()
+	{
+		let goal = Generator::snail(3);
+		let swapped = vec![2, 1, 3, 8, 0, 4, 7, 6, 5];
+		assert!(!is_solvable(&swapped, &goal, 3, false));
+		assert!(is_solvable(&swapped, &goal, 3, true));
+		assert!(oracle::distance(&swapped, 3, &Rules { torus: true, ..Rules::default() }, |map| *map == goal).is_some());
+		let mut solver = Solver::new(goal.clone(), 3, "manhattan", Flag::default(), Instant::now());
+		solver.set_torus().unwrap();
+		assert!(Solver::new(goal.clone(), 3, "conflict", Flag::default(), Instant::now()).set_torus().is_err());
+		let mut generator = Generator::new(3, None, "random", "snail", ".", Some(7));
+		for _ in 0..20
+		{
+			assert!(solver.is_solvable(&generator.random_map(&goal, false)).is_ok());
+		}
+	}
+
+	// Even tori keep the parity rule of flat boards
+	#[test]
+	fn even_torus()
+	{
+		let goal = Generator::snail(4);
+		let mut generator = Generator::new(4, Some(500), "normal", "snail", ".", Some(7));
+		generator.torus = true;
+		for _ in 0..20
+		{
+			let map = generator.shuffle_map(goal.clone(), Position { x: 1, y: 2 });
+			assert!(is_solvable(&map, &goal, 4, true));
+			assert!(!is_solvable(&generator.get_unsolvable(map), &goal, 4, true));
+		}
+	}
+
+	// Paths are optimal for the total weight of the moved tiles, and the weighted heuristic never overestimates it
+	#[test]
+	fn weighted_moves()
+	{
+		let goal = Generator::snail(3);
+		let weights: Vec<usize> = (0..9).collect();
+		let mut solver = Solver::new(goal.clone(), 3, "manhattan", Flag::default(), Instant::now());
+		solver.set_weights(weights.clone()).unwrap();
+		assert!(Solver::new(goal.clone(), 3, "conflict", Flag::default(), Instant::now()).set_weights(weights).is_err());
+
+		for start in [vec![5, 4, 3, 1, 0, 2, 6, 7, 8], vec![1, 2, 3, 8, 6, 4, 7, 0, 5], vec![8, 1, 3, 0, 2, 4, 7, 6, 5]]
+		{
+			let optimal = oracle::distance(&start, 3, &solver.rules, |map| *map == goal).unwrap();
+			assert_eq!(astar_hash_distributed::find_path(start.clone(), &solver).unwrap().cost, Some(optimal));
+			assert_eq!(astar::find_path(start.clone(), &solver).unwrap().cost, Some(optimal));
+			let mut node = Node::new(start);
+			node.find_position(3);
+			assert!(solver.get_cost(node).h <= optimal);
+		}
+	}
+
+	// Searches stop at the closest state placing the tiles of a partial goal
+	#[test]
+	fn partial_goal()
+	{
+		for (goal, heuristic) in [(vec![1, 2, 3, W, W, W, W, W, W], "conflict"), (vec![1, W, W, W, 0, W, W, W, 5], "manhattan")]
+		{
+			let solver = Solver::new(goal.clone(), 3, heuristic, Flag::default(), Instant::now());
+			for start in [vec![5, 4, 3, 1, 0, 2, 6, 7, 8], vec![8, 1, 3, 0, 2, 4, 7, 6, 5]]
+			{
+				let optimal = oracle::distance(&start, 3, &solver.rules, |map| solver.matches(map)).unwrap();
+				let solution = astar_hash_distributed::find_path(start.clone(), &solver).unwrap();
+				assert_eq!(solution.moves, optimal);
+				assert!(solver.matches(&solution.path[0].map));
+				assert_eq!(astar::find_path(start.clone(), &solver).unwrap().moves, optimal);
+				assert_eq!(astar_iterative_deepening::optimal_length(start, &solver), Some(optimal));
+			}
+		}
+	}
+
+	// The full goals standing for a partial one, checked against the states the start actually reaches
+	#[test]
+	fn completions_reachable()
+	{
+		let start = vec![1, 2, 3, 8, 0, 4, 7, 6, 5];
+		let swapped = vec![2, 1, 3, 8, 0, 4, 7, 6, 5];
+		let cases = [
+			// No wildcard: the goal itself
+			(Generator::snail(3), vec![Generator::snail(3)]),
+			// A single wildcard on a tile: its tile is the only one left, a single arrangement
+			(vec![W, 2, 3, 8, 0, 4, 7, 6, 5], vec![Generator::snail(3)]),
+			// A single wildcard on the blank: the same
+			(vec![1, 2, 3, 8, W, 4, 7, 6, 5], vec![Generator::snail(3)]),
+			// A wildcard on a tile and one on the blank: the free tile and the blank trade places
+			(vec![W, 2, 3, 8, W, 4, 7, 6, 5], vec![vec![0, 2, 3, 8, 1, 4, 7, 6, 5], vec![1, 2, 3, 8, 0, 4, 7, 6, 5]]),
+			// Two wildcards on tiles: both orders of the free tiles
+			(vec![W, W, 3, 8, 0, 4, 7, 6, 5], vec![Generator::snail(3), swapped.clone()])
+		];
+		for (goal, expected) in cases.iter()
+		{
+			assert_eq!(&completions(&start, goal), expected);
+		}
+
+		// A partial goal can be reached exactly when one of its completions can
+		for (goal, _) in cases.iter()
+		{
+			let solver = Solver::new(goal.clone(), 3, "manhattan", Flag::default(), Instant::now());
+			for from in [&start, &swapped]
+			{
+				let reachable = oracle::distance(from, 3, &solver.rules, |map| solver.matches(map)).is_some();
+				assert_eq!(solver.is_solvable(from).is_ok(), reachable);
+			}
+		}
+	}
+}
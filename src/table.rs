@@ -0,0 +1,199 @@
+use std::collections::VecDeque;
+use crate::{Map, Move, Position};
+use crate::display::State;
+
+const SIZE: usize = 3;
+const TILES: usize = SIZE * SIZE;
+// 9! permutations, half of them can't be reached from the goal
+const STATES: usize = 362880;
+const UNREACHABLE: u8 = u8::MAX;
+
+// Exact distance of every 3x3 state to a goal, found by a backward breadth first search.
+// Each state is stored as a single byte at the rank of its permutation.
+pub struct Table
+{
+	pub goal: Map,
+	distance: Vec<u8>
+}
+
+// Lehmer code of the permutation, from 0 to 9! - 1
+fn rank(map: &Map) -> usize
+{
+	let mut rank = 0;
+	for i in 0..TILES
+	{
+		let smaller = map[i + 1..].iter().filter(|&&x| x < map[i]).count();
+		rank = rank * (TILES - i) + smaller;
+	}
+	rank
+}
+
+fn unrank(mut rank: usize) -> Map
+{
+	let mut digits = [0; TILES];
+	for i in (0..TILES).rev()
+	{
+		digits[i] = rank % (TILES - i);
+		rank /= TILES - i;
+	}
+	let mut tiles: Vec<usize> = (0..TILES).collect();
+	digits.iter().map(|&d| tiles.remove(d)).collect()
+}
+
+fn blank(map: &Map) -> Position
+{
+	let index = map.iter().position(|&x| x == 0).unwrap();
+	Position { x: index % SIZE, y: index / SIZE }
+}
+
+fn neighbours(map: &Map) -> Vec<(Move, Map)>
+{
+	let pos = blank(map);
+	pos.possible_moves(SIZE)
+		.into_iter()
+		.filter(|m| *m != Move::No)
+		.map(|m| { let next = m.do_move(map.clone(), &pos, SIZE); (m, next) })
+		.collect()
+}
+
+impl Table
+{
+	pub fn new(goal: &Map) -> Self
+	{
+		let mut distance = vec![UNREACHABLE; STATES];
+		let mut queue: VecDeque<usize> = VecDeque::new();
+		distance[rank(goal)] = 0;
+		queue.push_back(rank(goal));
+
+		while let Some(current) = queue.pop_front()
+		{
+			let next_distance = distance[current] + 1;
+			for (_, map) in neighbours(&unrank(current))
+			{
+				let index = rank(&map);
+				if distance[index] != UNREACHABLE { continue }
+				distance[index] = next_distance;
+				queue.push_back(index);
+			}
+		}
+		Self { goal: goal.clone(), distance }
+	}
+
+	// Number of moves of an optimal solution, None if the state can't reach the goal
+	pub fn distance(&self, map: &Map) -> Option<usize>
+	{
+		match self.distance[rank(map)]
+		{
+			UNREACHABLE => None,
+			d => Some(d as usize)
+		}
+	}
+
+	// A move of an optimal solution, Move::No if the state is the goal
+	pub fn next_move(&self, map: &Map) -> Option<Move>
+	{
+		let distance = self.distance(map)?;
+		if distance == 0 { return Some(Move::No) }
+		neighbours(map)
+			.into_iter()
+			.find(|(_, next)| self.distance(next) == Some(distance - 1))
+			.map(|(movement, _)| movement)
+	}
+
+	// Optimal path from the state to the goal, in the order of display::Solution
+	// (goal first and start state last)
+	pub fn path(&self, map: &Map) -> Option<Vec<State>>
+	{
+		let mut path = vec![State { map: map.clone(), movement: Move::No }];
+		let mut current = map.clone();
+		loop
+		{
+			let movement = self.next_move(&current)?;
+			if movement == Move::No { break }
+			current = movement.do_move(current.clone(), &blank(&current), SIZE);
+			path.push(State { map: current.clone(), movement });
+		}
+		path.reverse();
+		Some(path)
+	}
+
+	// Every reachable state at a distance within [min, max]
+	pub fn states(&self, min: usize, max: usize) -> Vec<Map>
+	{
+		(0..STATES)
+			.filter(|&i| self.distance[i] != UNREACHABLE)
+			.filter(|&i| min <= self.distance[i] as usize && self.distance[i] as usize <= max)
+			.map(unrank)
+			.collect()
+	}
+}
+
+#[cfg(test)]
+mod tests
+{
+	use std::time::Instant;
+	use crate::{Flag, Move};
+	use crate::solver::Solver;
+	use crate::generator::Generator;
+	use crate::{astar, astar_iterative_deepening};
+	use super::Table;
+
+	#[test]
+	fn rank_unrank()
+	{
+		for &rank in &[0, 1, 4242, 181440, 362879]
+		{
+			assert_eq!(super::rank(&super::unrank(rank)), rank);
+		}
+		assert_eq!(super::unrank(0), (0..9).collect::<Vec<usize>>());
+	}
+
+	#[test]
+	fn reachable_states()
+	{
+		let table = Table::new(&Generator::classic(3));
+		assert_eq!(table.states(0, 100).len(), 181440);
+		assert_eq!(table.states(31, 31).len(), 2);
+		assert_eq!(table.distance(&vec![2, 1, 3, 4, 5, 6, 7, 8, 0]), None);
+		assert_eq!(table.distance(&vec![1, 2, 3, 4, 5, 6, 7, 0, 8]), Some(1));
+	}
+
+	#[test]
+	fn optimal_path()
+	{
+		let goal = Generator::snail(3);
+		let table = Table::new(&goal);
+		let start = vec![5, 4, 3, 1, 0, 2, 6, 7, 8];
+		let path = table.path(&start).unwrap();
+		assert_eq!(path.len() - 1, table.distance(&start).unwrap());
+		assert_eq!(path[0].map, goal);
+		assert_eq!(path.last().unwrap().map, start);
+		assert_eq!(path.last().unwrap().movement, Move::No);
+		for step in path.windows(2)
+		{
+			assert_eq!(table.distance(&step[1].map), table.distance(&step[0].map).map(|d| d + 1));
+		}
+	}
+
+	// Every admissible heuristic must lead A* and IDA* to an optimal solution
+	#[test]
+	fn optimal_algorithms()
+	{
+		for goal in &["snail", "classic", "reversed"]
+		{
+			let goal = Generator::generate_goal(goal, 3);
+			let table = Table::new(&goal);
+			let states = table.states(1, 16);
+			for heuristic in &["misplaced", "manhattan", "axes", "conflict"]
+			{
+				let solver = Solver::new(goal.clone(), 3, heuristic, Flag::default(), Instant::now());
+				for start in states.iter().step_by(997)
+				{
+					let expected = table.distance(start);
+					assert_eq!(astar::find_path(start.clone(), &solver).unwrap().moves, expected.unwrap());
+					assert_eq!(astar_iterative_deepening::optimal_length(start.clone(), &solver), expected);
+				}
+			}
+		}
+	}
+}
@@ -0,0 +1,216 @@
+use std::collections::HashMap;
+use std::fs::File;
+use std::io::Write;
+
+use crate::{Map, Move};
+use crate::node::Node;
+use crate::display::State;
+use crate::error::Error;
+
+// A state of the explored graph with the edge through which it was reached with the lowest g
+pub struct Vertex
+{
+	pub map: Map,
+	pub g: usize,
+	pub h: usize,
+	pub f: usize,
+	// Rank at which the state was expanded, None if it stayed in the open set
+	pub order: Option<usize>,
+	pub parent: Option<(usize, Move)>,
+	pub solution: bool
+}
+
+// Graph explored by a search, limited to the first 'cap' states generated
+pub struct Tree
+{
+	pub size: usize,
+	pub vertices: Vec<Vertex>,
+	index: HashMap<Map, usize>,
+	cap: usize,
+	expanded: usize
+}
+
+impl Tree
+{
+	pub fn new(size: usize, cap: usize) -> Self
+	{
+		Self { size, vertices: vec![], index: HashMap::new(), cap, expanded: 0 }
+	}
+
+	// Record a state generated from the vertex 'parent', or keep its cheapest parent if it is already known
+	pub fn generated(&mut self, node: &Node, parent: Option<usize>)
+	{
+		let parent = parent.map(|index| (index, node.movement.clone()));
+		if let Some(&index) = self.index.get(&node.map)
+		{
+			let vertex = &mut self.vertices[index];
+			if node.g < vertex.g
+			{
+				vertex.g = node.g;
+				vertex.h = node.h;
+				vertex.f = node.f;
+				vertex.parent = parent;
+			}
+			return
+		}
+		if self.vertices.len() >= self.cap { return }
+		self.index.insert(node.map.clone(), self.vertices.len());
+		self.vertices.push(Vertex { map: node.map.clone(), g: node.g, h: node.h, f: node.f, order: None, parent, solution: false });
+	}
+
+	// Mark the state as expanded, returns its vertex if it is part of the graph
+	pub fn expanded(&mut self, node: &Node) -> Option<usize>
+	{
+		let index = *self.index.get(&node.map)?;
+		if self.vertices[index].order.is_none()
+		{
+			self.expanded += 1;
+			self.vertices[index].order = Some(self.expanded);
+		}
+		Some(index)
+	}
+
+	// Mark the states of a solution (in the order of display::Solution) and the edges between them
+	pub fn highlight(&mut self, path: &[State])
+	{
+		for step in path.windows(2)
+		{
+			let (child, parent) = (self.index.get(&step[0].map), self.index.get(&step[1].map));
+			if let (Some(&child), Some(&parent)) = (child, parent)
+			{
+				self.vertices[child].parent = Some((parent, step[0].movement.clone()));
+				self.vertices[child].solution = true;
+				self.vertices[parent].solution = true;
+			}
+		}
+		if let Some(&start) = path.last().and_then(|state| self.index.get(&state.map))
+		{
+			self.vertices[start].solution = true;
+		}
+	}
+
+	fn grid(&self, map: &Map, separator: &str) -> String
+	{
+		map
+			.chunks(self.size)
+			.map(|row| row.iter().map(|x| x.to_string()).collect::<Vec<String>>().join(" "))
+			.collect::<Vec<String>>()
+			.join(separator)
+	}
+
+	pub fn to_dot(&self) -> String
+	{
+		let mut dot = String::from("digraph search {\n\tnode [shape=box, fontname=\"monospace\"];\n");
+		for (id, vertex) in self.vertices.iter().enumerate()
+		{
+			let order = match vertex.order
+			{
+				Some(order) => format!("#{}", order),
+				None => "open".to_owned()
+			};
+			let mut style = vec![];
+			if vertex.order.is_none() { style.push("style=dashed".to_owned()) }
+			if vertex.solution { style.push("color=red, penwidth=2".to_owned()) }
+			dot.push_str(&format!("\tn{} [label=\"{}\\ng={} h={} f={}\\n{}\"{}];\n",
+				id, self.grid(&vertex.map, "\\n"), vertex.g, vertex.h, vertex.f, order,
+				style.iter().map(|s| format!(", {}", s)).collect::<String>()));
+		}
+		for (id, vertex) in self.vertices.iter().enumerate()
+		{
+			if let Some((parent, movement)) = &vertex.parent
+			{
+				let style = match vertex.solution && self.vertices[*parent].solution
+				{
+					true => ", color=red, penwidth=2",
+					false => ""
+				};
+				dot.push_str(&format!("\tn{} -> n{} [label=\"{}\"{}];\n", parent, id, movement, style));
+			}
+		}
+		dot.push_str("}\n");
+		dot
+	}
+
+	pub fn to_json(&self) -> String
+	{
+		let vertices: Vec<String> = self.vertices
+			.iter()
+			.enumerate()
+			.map(|(id, vertex)|
+			{
+				let (parent, movement) = match &vertex.parent
+				{
+					Some((parent, movement)) => (parent.to_string(), format!("\"{}\"", movement)),
+					None => ("null".to_owned(), "null".to_owned())
+				};
+				format!("\t\t{{\"id\": {}, \"tiles\": [{}], \"g\": {}, \"h\": {}, \"f\": {}, \"order\": {}, \"parent\": {}, \"move\": {}, \"solution\": {}}}",
+					id,
+					vertex.map.iter().map(|x| x.to_string()).collect::<Vec<String>>().join(", "),
+					vertex.g, vertex.h, vertex.f,
+					vertex.order.map_or("null".to_owned(), |order| order.to_string()),
+					parent, movement, vertex.solution)
+			})
+			.collect();
+		format!("{{\n\t\"size\": {},\n\t\"nodes\":\n\t[\n{}\n\t]\n}}\n", self.size, vertices.join(",\n"))
+	}
+
+	// Write the graph as JSON if the file ends with '.json', as Graphviz DOT otherwise
+	pub fn create_file(&self, filepath: &str) -> Result<(), Error>
+	{
+		let data = match filepath.ends_with(".json")
+		{
+			true => self.to_json(),
+			false => self.to_dot()
+		};
+		let mut file = match File::create(filepath)
+		{
+			Ok(f) => Ok(f),
+			Err(e) => Err(Error::Io(format!("unable to create '{}': {}", filepath, e)))
+		}?;
+		if let Err(e) = file.write_all(data.as_bytes())
+		{
+			return Err(Error::Io(format!("unable to write '{}': {}", filepath, e)))
+		};
+		Ok(())
+	}
+}
+
+#[cfg(test)]
+mod tests
+{
+	use std::time::Instant;
+	use crate::Flag;
+	use crate::solver::Solver;
+	use crate::generator::Generator;
+	use crate::astar;
+
+	#[test]
+	fn explored_graph()
+	{
+		let solver = Solver::new(Generator::snail(3), 3, "manhattan", Flag::default(), Instant::now());
+		let start = vec![5, 4, 3, 1, 0, 2, 6, 7, 8];
+		let (solution, tree) = astar::find_path_tree(start.clone(), &solver, 100000).unwrap();
+
+		assert_eq!(tree.vertices[0].map, start);
+		assert_eq!(tree.vertices.iter().filter(|v| v.solution).count(), solution.moves + 1);
+		let mut orders: Vec<usize> = tree.vertices.iter().filter_map(|v| v.order).collect();
+		orders.sort_unstable();
+		assert_eq!(orders, (1..=orders.len()).collect::<Vec<usize>>());
+		for vertex in tree.vertices.iter().skip(1)
+		{
+			let (parent, movement) = vertex.parent.as_ref().unwrap();
+			let parent = &tree.vertices[*parent];
+			assert!(parent.order.is_some());
+			assert_eq!(vertex.map.iter().zip(&parent.map).filter(|(a, b)| a != b).count(), 2);
+			assert_eq!(vertex.map.iter().position(|&x| x == 0).unwrap() as i64,
+				parent.map.iter().position(|&x| x == 0).unwrap() as i64 + movement.get_offset());
+		}
+		let goal = tree.vertices.iter().find(|v| v.map == solver.goal).unwrap();
+		assert!(goal.solution && goal.g == solution.moves);
+
+		let (_, tree) = astar::find_path_tree(start, &solver, 10).unwrap();
+		assert_eq!(tree.vertices.len(), 10);
+		assert!(tree.to_dot().starts_with("digraph search {"));
+		assert_eq!(tree.to_json().matches("\"id\"").count(), 10);
+	}
+}
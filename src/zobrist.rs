@@ -0,0 +1,151 @@
+use std::collections::{HashMap, HashSet};
+use std::hash::{BuildHasherDefault, Hash, Hasher};
+
+use crate::Map;
+use crate::node::Node;
+
+// Pseudo-random key of a tile on a cell, derived from both so that no table has to be shared.
+// The blanks have none, a state's hash being the xor of the keys of its tiles.
+#[inline]
+pub fn key(cell: usize, tile: usize) -> u64
+{
+	if tile == 0 { return 0 }
+	mix((cell as u64) << 32 ^ tile as u64)
+}
+
+// Finalizer of splitmix64, spreading any change of its input over the whole output
+#[inline]
+pub fn mix(x: u64) -> u64
+{
+	let mut x = x.wrapping_add(0x9E37_79B9_7F4A_7C15);
+	x = (x ^ (x >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+	x = (x ^ (x >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+	x ^ (x >> 31)
+}
+
+// Hash of a whole state, nodes updating theirs with the cells a move changes
+pub fn hash(map: &Map) -> u64
+{
+	map.iter().enumerate().fold(0, |hash, (cell, &tile)| hash ^ key(cell, tile))
+}
+
+// Entry of a closed set: the Zobrist hash of a state, with the state itself when two states
+// of equal hashes must be told apart
+#[derive(Debug, Clone, Eq)]
+pub struct Key
+{
+	pub hash: u64,
+	pub map: Option<Map>
+}
+
+impl Key
+{
+	pub fn new(node: &Node, confirm: bool) -> Self
+	{
+		Self { hash: node.hash, map: if confirm { Some(node.map.clone()) } else { None } }
+	}
+
+	pub fn of(map: &Map, confirm: bool) -> Self
+	{
+		Self { hash: hash(map), map: if confirm { Some(map.clone()) } else { None } }
+	}
+}
+
+impl PartialEq for Key
+{
+	fn eq(&self, other: &Key) -> bool
+	{
+		self.hash == other.hash && self.map == other.map
+	}
+}
+
+impl Hash for Key
+{
+	fn hash<H: Hasher>(&self, state: &mut H)
+	{
+		state.write_u64(self.hash);
+	}
+}
+
+// Hasher keeping the Zobrist hash as it is, since it is already evenly spread
+#[derive(Default)]
+pub struct PassThrough(u64);
+
+impl Hasher for PassThrough
+{
+	fn finish(&self) -> u64
+	{
+		self.0
+	}
+
+	fn write(&mut self, bytes: &[u8])
+	{
+		for byte in bytes { self.0 = self.0.rotate_left(8) ^ u64::from(*byte) }
+	}
+
+	fn write_u64(&mut self, n: u64)
+	{
+		self.0 = n;
+	}
+}
+
+pub type ZobristMap<V> = HashMap<Key, V, BuildHasherDefault<PassThrough>>;
+pub type ZobristSet = HashSet<Key, BuildHasherDefault<PassThrough>>;
+
+#[cfg(test)]
+mod tests
+{
+	use std::collections::HashSet;
+	use crate::{Map, Rules};
+	use crate::node::Node;
+	use crate::generator::Generator;
+	use super::{hash, Key, ZobristSet};
+
+	// Every state reachable in a few moves, with the rules given
+	fn states(start: Map, size: usize, rules: &Rules, depth: usize) -> Vec<Node>
+	{
+		let mut node = Node::new(start);
+		node.find_position(size);
+		let mut layer = vec![node];
+		let mut all = vec![];
+		for _ in 0..depth
+		{
+			layer = layer.iter().flat_map(|node| node.generate_moves(size, rules)).collect();
+			all.extend(layer.iter().cloned());
+		}
+		all
+	}
+
+	// Hashes updated move by move match the hash of the whole state, with every kind of move
+	#[test]
+	fn incremental()
+	{
+		let torus = Rules { torus: true, ..Rules::default() };
+		let slides = Rules { slides: true, ..Rules::default() };
+		for (start, rules) in [(Generator::snail(4), Rules::default()), (Generator::snail(4), torus), (Generator::snail(4), slides),
+			(Generator::with_blanks(Generator::snail(4), 3), Rules::default())]
+		{
+			for node in states(start, 4, &rules, 4)
+			{
+				assert_eq!(node.hash, hash(&node.map));
+			}
+		}
+	}
+
+	// Distinct states get distinct hashes, and confirmed keys still tell apart states of equal hashes
+	#[test]
+	fn keys()
+	{
+		let nodes = states(Generator::snail(3), 3, &Rules::default(), 12);
+		let maps: HashSet<Map> = nodes.iter().map(|node| node.map.clone()).collect();
+		let hashes: HashSet<u64> = nodes.iter().map(|node| node.hash).collect();
+		assert_eq!(maps.len(), hashes.len());
+
+		let mut set = ZobristSet::default();
+		let (a, b) = (vec![1, 2, 3, 8, 0, 4, 7, 6, 5], vec![2, 1, 3, 8, 0, 4, 7, 6, 5]);
+		set.insert(Key { hash: 7, map: Some(a.clone()) });
+		assert!(set.contains(&Key { hash: 7, map: Some(a) }));
+		assert!(!set.contains(&Key { hash: 7, map: Some(b) }));
+		assert!(!set.contains(&Key { hash: 7, map: None }));
+	}
+}